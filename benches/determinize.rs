@@ -0,0 +1,69 @@
+// Times the conversion of large NFAs to DFAs by running the built program.
+// Run with `cargo bench`.
+
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+use std::time::Instant;
+
+const RUNS:usize = 5;
+
+fn program() -> Command {
+	return Command::new(env!("CARGO_BIN_EXE_Finite_Automata_Project"));
+}
+
+/// The fastest of several runs of the program with the given arguments.
+fn time(args:&[&str]) -> Duration {
+	let mut best = Duration::MAX;
+	for _ in 0..RUNS {
+		let start = Instant::now();
+		let output = program().args(args).output().expect("Could not run the program");
+		let elapsed = start.elapsed();
+		if !output.status.success() {
+			panic!("The program failed: {}",String::from_utf8_lossy(&output.stderr));
+		}
+		best = best.min(elapsed);
+	}
+	return best;
+}
+
+/// Writes an NFA with a chain of states, each jumping to every seventh state after it, so closures are large.
+fn write_chain_nfa(path:&PathBuf, num_states:usize) {
+	let mut lines:Vec<String> = vec!["ab".to_string(),"1".to_string()];
+	for i in 1..=num_states {
+		let mut line = String::new();
+		line.push_str(&format!("a:{},",i));
+		if i < num_states {
+			line.push_str(&format!("b:{},",i+1));
+		}
+		for j in ((i+1)..=num_states).step_by(7) {
+			line.push_str(&format!(":{},",j));
+		}
+		line.push_str(&(i == num_states).to_string());
+		lines.push(line);
+	}
+	std::fs::write(path,lines.join("\n")).expect("Could not write the benchmark NFA");
+}
+
+fn main() {
+	let dir = std::env::temp_dir().join("determinize_bench");
+	std::fs::create_dir_all(&dir).expect("Could not create the benchmark directory");
+	let dfa = dir.join("out.dfa");
+	let dfa = dfa.to_str().unwrap();
+
+	// the NFA has about 4n states and its DFA 2^(n+1)
+	for n in [6,9,12] {
+		let regex = format!("(a|b)*a(a|b){{{}}}",n);
+		let elapsed = time(&["-r",&regex,"-a","ab","-d",dfa]);
+		println!("regex {:<20} {:>10.2?}",regex,elapsed);
+	}
+
+	for num_states in [100,300,600] {
+		let nfa = dir.join(format!("chain{}.nfa",num_states));
+		write_chain_nfa(&nfa,num_states);
+		let elapsed = time(&["-i",nfa.to_str().unwrap(),"-d",dfa]);
+		println!("chain NFA of {:>4} states {:>10.2?}",num_states,elapsed);
+	}
+}
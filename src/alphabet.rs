@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use crate::Ordered;
+use crate::nfa::NFA;
+use crate::nfa::NFAState;
+use crate::dfa::DFA;
+use crate::dfa::DFAState;
+
+/*
+Changes to the alphabet of an automaton. Extending and restricting keep the states as they are, changing only which letters have transitions, while renaming and projecting can merge the transitions of several letters, so for NFAs only.
+*/
+
+/// The letters of the alphabet that are in the given set, in the order of the alphabet, with their positions in it.
+fn kept_letters(alphabet:&str, letters:&str) -> Vec<(usize,char)> {
+	return alphabet.chars().enumerate().filter(|(_,c)| letters.contains(*c)).collect();
+}
+
+/// The alphabet followed by the given letters that are not already in it.
+fn extended(alphabet:&str, letters:&str) -> String {
+	let mut result = alphabet.to_string();
+	for c in letters.chars() {
+		if !result.contains(c) {
+			result.push(c);
+		}
+	}
+	return result;
+}
+
+impl DFA {
+	/// The same DFA over the alphabet with the given letters added. The new letters go to the implicit sink state, so the language is unchanged.
+	pub fn extend_alphabet(&self, letters:&str) -> DFA {
+		let alphabet = extended(&self.alphabet,letters);
+		let added = alphabet.chars().count()-self.alphabet.chars().count();
+		let states = self.states.iter().map(|s| {
+			let mut transitions = s.transitions.clone();
+			transitions.extend(vec![None;added]);
+			DFAState::new(transitions,s.accepting)
+		}).collect();
+		return DFA::new(states,alphabet,self.starting);
+	}
+
+	/// The same DFA over only the letters of the alphabet that are in the given set, dropping the transitions on the other letters. It accepts the words of the language that use only those letters.
+	pub fn restrict_alphabet(&self, letters:&str) -> DFA {
+		let kept = kept_letters(&self.alphabet,letters);
+		let states = self.states.iter().map(|s| {
+			DFAState::new(kept.iter().map(|(i,_)| s.transitions[*i]).collect(),s.accepting)
+		}).collect();
+		return DFA::new(states,kept.iter().map(|(_,c)| *c).collect(),self.starting);
+	}
+}
+
+impl NFA {
+	/// The same NFA over the alphabet with the given letters added, which have no transitions.
+	pub fn extend_alphabet(&self, letters:&str) -> NFA {
+		return self.with_alphabet(&extended(&self.alphabet,letters));
+	}
+
+	/// The same NFA over only the letters of the alphabet that are in the given set, dropping the transitions on the other letters.
+	pub fn restrict_alphabet(&self, letters:&str) -> NFA {
+		let kept = kept_letters(&self.alphabet,letters);
+		let states = self.states.iter().map(|s| {
+			let mut transitions = vec![s.transitions[0].clone()];
+			transitions.extend(kept.iter().map(|(i,_)| s.transitions[i+1].clone()));
+			NFAState::new(transitions,s.accepting)
+		}).collect();
+		return NFA::new(states,self.starting,kept.iter().map(|(_,c)| *c).collect());
+	}
+
+	/// The same NFA with its letters renamed by the map, leaving the letters not in the map unchanged. Letters renamed to the same letter, or to a letter already in the alphabet, have their transitions merged.
+	pub fn rename_letters(&self, map:&HashMap<char,char>) -> Result<NFA,String> {
+		let mut from:Vec<&char> = map.keys().collect();
+		from.sort();
+		for c in from {
+			if !self.alphabet.contains(*c) {
+				return Err(format!("The letter {} cannot be renamed as it is not in the alphabet",c));
+			}
+		}
+		let renamed:Vec<char> = self.alphabet.chars().map(|c| *map.get(&c).unwrap_or(&c)).collect();
+		let alphabet = extended("",&renamed.iter().collect::<String>());
+		let index = crate::get_alphabet_hm(&alphabet);
+		let states = self.states.iter().map(|s| {
+			let mut transitions:Vec<Ordered> = vec![Ordered(Vec::new());index.len()+1];
+			transitions[0] = s.transitions[0].clone();
+			for (i,c) in renamed.iter().enumerate() {
+				let to = index[c].0+1;
+				transitions[to] = transitions[to].join(&s.transitions[i+1]);
+			}
+			NFAState::new(transitions,s.accepting)
+		}).collect();
+		return Ok(NFA::new(states,self.starting,alphabet));
+	}
+
+	/// The projection erasing the given letters: their transitions become jumps and they are removed from the alphabet, so the NFA accepts the words of the language with those letters deleted.
+	pub fn project(&self, letters:&str) -> NFA {
+		let kept:String = self.alphabet.chars().filter(|c| !letters.contains(*c)).collect();
+		let mut result = self.restrict_alphabet(&kept);
+		for (state,s) in self.states.iter().enumerate() {
+			for (i,_) in kept_letters(&self.alphabet,letters) {
+				result.states[state].transitions[0] = result.states[state].transitions[0].join(&s.transitions[i+1]);
+			}
+		}
+		return result;
+	}
+}
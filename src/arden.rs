@@ -0,0 +1,147 @@
+use crate::StateNum;
+use crate::regex::RegexTree;
+use crate::regex::Regex;
+use crate::nfa::NFA;
+
+use crate::Index1;
+
+use crate::limits::Budget;
+use crate::limits::LimitError;
+use crate::limits::Progress;
+
+/*
+Brzozowski's algebraic method. Each state i of the automaton has a variable X_i for the language accepted from it, which satisfies the right-linear equation
+
+X_i = A_i1 X_1 | ... | A_in X_n | B_i
+
+where A_ij is the alternation of the letters on edges from i to j, and B_i is ε if i is accepting. Arden's lemma solves an equation X = A X | B as X = A* B, so solving the equations one variable at a time, and substituting each solution into the remaining equations, leaves the regex accepted from the starting state.
+*/
+
+/// One equation, with the coefficient of each variable and the constant term. None is the empty language.
+struct Equation {
+	coefficients:Vec<Option<RegexTree>>,
+	constant:Option<RegexTree>
+}
+
+fn get_equation(nfa:&NFA, state:StateNum) -> Equation {
+	let num_states = nfa.states.len();
+	let mut coefficients:Vec<Option<RegexTree>> = vec![None;num_states];
+	for (letter,transitions) in nfa.states[state].transitions.iter().enumerate() {
+		let term = match letter {
+			0 => RegexTree::Empty,
+			l => RegexTree::Single(Index1(l).into())
+		};
+		for next in &transitions.0 {
+			coefficients[*next] = RegexTree::or_opt(coefficients[*next].clone(),Some(term.clone()));
+		}
+	}
+	let constant = match nfa.states[state].accepting {
+		true => Some(RegexTree::Empty),
+		false => None
+	};
+	return Equation{coefficients,constant};
+}
+
+/// Replaces X = A X | B by the solution X = A* B.
+fn apply_arden(equation:&mut Equation, variable:StateNum) {
+	let a = match equation.coefficients[variable].take() {
+		None => return,
+		a => a
+	};
+	let a_star = RegexTree::star_opt(a);
+	for coefficient in equation.coefficients.iter_mut() {
+		if coefficient.is_some() {
+			*coefficient = RegexTree::concat_opt(a_star.clone(),coefficient.take());
+		}
+	}
+	if equation.constant.is_some() {
+		equation.constant = RegexTree::concat_opt(a_star,equation.constant.take());
+	}
+}
+
+/// Replaces the variable in the target equation by the solution for it.
+fn substitute(target:&mut Equation, solution:&Equation, variable:StateNum) {
+	let c = match target.coefficients[variable].take() {
+		None => return,
+		c => c
+	};
+	for (j,coefficient) in solution.coefficients.iter().enumerate() {
+		if coefficient.is_some() {
+			target.coefficients[j] = RegexTree::or_opt(target.coefficients[j].take(),RegexTree::concat_opt(c.clone(),coefficient.clone()));
+		}
+	}
+	target.constant = RegexTree::or_opt(target.constant.take(),RegexTree::concat_opt(c,solution.constant.clone()));
+}
+
+/// Converts by solving the equations of the automaton with Arden's lemma, without simplifying the result. Returns None once a solution is as large as the bound.
+pub fn nfa_to_regex_arden(nfa:&NFA, bound:Option<usize>, budget:&Budget) -> Result<Option<Regex>,LimitError> {
+	let num_states = nfa.states.len();
+	let mut equations:Vec<Equation> = (0..num_states).map(|i| get_equation(nfa,i)).collect();
+	// the starting state is solved last, so its solution never needs substituting back
+	let order:Vec<StateNum> = (0..num_states).filter(|i| *i != nfa.starting).chain([nfa.starting]).collect();
+	for (position,variable) in order.iter().enumerate() {
+		let progress = Progress::Solving{solved:position,total:num_states};
+		if let Err(e) = budget.check_time(progress) {
+			return Err(e);
+		}
+		apply_arden(&mut equations[*variable],*variable);
+		let solution = Equation{
+			coefficients:equations[*variable].coefficients.clone(),
+			constant:equations[*variable].constant.clone()
+		};
+		for other in &order[position+1..] {
+			substitute(&mut equations[*other],&solution,*variable);
+		}
+		if let Some(bound) = bound {
+			if largest_term(&equations[*variable]) >= bound {
+				return Ok(None);
+			}
+		}
+		if let Err(e) = budget.check_regex_size(|| largest_term(&equations[*variable]),progress) {
+			return Err(e);
+		}
+	}
+	return Ok(Some(Regex::new(nfa.alphabet.clone(),equations[nfa.starting].constant.clone())));
+}
+
+fn largest_term(equation:&Equation) -> usize {
+	return equation.coefficients.iter().chain([&equation.constant])
+		.flatten()
+		.map(|r| r.size())
+		.max()
+		.unwrap_or(0);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dfa::DFA;
+	use crate::limits::Limits;
+
+	const CASES:[&str;10] = [
+		"a","ε","\\0","ab|ba","(a|b)*abb","(ab)*|c+","a*b*c*","((a|b)c)*a?","(a{2,3}b)*|c{2}","(a|ε)(b*|ca)*"
+	];
+
+	fn solved(nfa:&NFA) -> DFA {
+		let regex = nfa_to_regex_arden(nfa,None,&Limits::default().start()).unwrap().unwrap();
+		return DFA::from(&NFA::from(&regex));
+	}
+
+	#[test]
+	fn same_language_as_thompson() {
+		for regex in CASES {
+			let regex = Regex::parse(regex,Some("abc"),false).unwrap();
+			let thompson = NFA::from(&regex);
+			let expected = DFA::from(&thompson);
+			assert_eq!(solved(&thompson).distinguishing_word(&expected),None,"{}",regex);
+			// the states of a DFA have no jumps, and often several equations that refer to each other
+			assert_eq!(solved(&NFA::from(&expected)).distinguishing_word(&expected),None,"{}",regex);
+		}
+	}
+
+	#[test]
+	fn abandons_at_the_bound() {
+		let nfa = NFA::from(&Regex::parse("(a|b)*abb",None,false).unwrap());
+		assert!(nfa_to_regex_arden(&nfa,Some(3),&Limits::default().start()).unwrap().is_none());
+	}
+}
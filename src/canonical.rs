@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::StateNum;
+use crate::dfa::DFA;
+use crate::dfa::DFAState;
+
+/*
+Minimal DFAs for a language are unique up to the numbering of their states. Numbering the states in the order a breadth first search from the starting state reaches them, following the letters in sorted order, fixes the numbering, so the canonical form of the minimal DFA identifies the language.
+*/
+
+impl DFA {
+	/// The equivalent complete DFA with the fewest states, in canonical form. States are merged by Moore's algorithm, splitting the states into classes until states in the same class agree on which class each letter leads to.
+	pub fn minimize(&self) -> DFA {
+		// a complete DFA has no transitions to the implicit sink state, so they can be unwrapped
+		let dfa = self.to_complete().canonicalize();
+		let num_states = dfa.states.len();
+		let mut class:Vec<usize> = dfa.states.iter().map(|s| s.accepting as usize).collect();
+		let mut num_classes = 0;
+		loop {
+			let mut signatures:HashMap<(usize,Vec<usize>),usize> = HashMap::new();
+			let next_class:Vec<usize> = (0..num_states).map(|s| {
+				let signature = (class[s],dfa.states[s].transitions.iter().map(|t| class[t.unwrap()]).collect());
+				let next = signatures.len();
+				*signatures.entry(signature).or_insert(next)
+			}).collect();
+			class = next_class;
+			if signatures.len() == num_classes {
+				break;
+			}
+			num_classes = signatures.len();
+		}
+
+		let mut states:Vec<Option<DFAState>> = vec![None;num_classes];
+		for s in 0..num_states {
+			if states[class[s]].is_none() {
+				let transitions = dfa.states[s].transitions.iter().map(|t| Some(class[t.unwrap()])).collect();
+				states[class[s]] = Some(DFAState::new(transitions,dfa.states[s].accepting));
+			}
+		}
+		let states = states.into_iter().map(|s| s.unwrap()).collect();
+		return DFA::new(states,dfa.alphabet.clone(),class[dfa.starting]).canonicalize();
+	}
+
+	/// The same DFA with the alphabet sorted, the unreachable states removed, and the states numbered in breadth first order from the starting state.
+	pub fn canonicalize(&self) -> DFA {
+		let mut letters:Vec<(char,usize)> = self.alphabet.chars().enumerate().map(|(i,c)| (c,i)).collect();
+		letters.sort();
+		let alphabet:String = letters.iter().map(|(c,_)| *c).collect();
+
+		let mut number:Vec<Option<StateNum>> = vec![None;self.states.len()];
+		let mut order:Vec<StateNum> = vec![self.starting];
+		number[self.starting] = Some(0);
+		let mut next = 0;
+		while next < order.len() {
+			let state = order[next];
+			for (_,letter) in &letters {
+				if let Some(target) = self.states[state].transitions[*letter] {
+					if number[target].is_none() {
+						number[target] = Some(order.len());
+						order.push(target);
+					}
+				}
+			}
+			next += 1;
+		}
+
+		let states = order.iter().map(|s| {
+			let transitions = letters.iter().map(|(_,letter)| self.states[*s].transitions[*letter].map(|t| number[t].unwrap())).collect();
+			DFAState::new(transitions,self.states[*s].accepting)
+		}).collect();
+		return DFA::new(states,alphabet,0);
+	}
+
+	/// The numbering of the states of the other DFA matching each state of this one, if the DFAs are the same up to numbering. Every state must be reachable from the starting state for a match to be found, and transitions to the implicit sink state only match each other.
+	pub fn is_isomorphic(&self, other:&DFA) -> Option<Vec<StateNum>> {
+		let letters:Vec<char> = self.alphabet.chars().collect();
+		let other_letters:Vec<char> = other.alphabet.chars().collect();
+		if self.states.len() != other.states.len() || letters.len() != other_letters.len() {
+			return None;
+		}
+		let mut other_index:Vec<usize> = Vec::new();
+		for c in &letters {
+			match other_letters.iter().position(|o| o == c) {
+				Some(i) => other_index.push(i),
+				None => return None
+			}
+		}
+
+		let mut mapping:Vec<Option<StateNum>> = vec![None;self.states.len()];
+		let mut used = vec![false;other.states.len()];
+		let mut order:Vec<StateNum> = vec![self.starting];
+		mapping[self.starting] = Some(other.starting);
+		used[other.starting] = true;
+		let mut next = 0;
+		while next < order.len() {
+			let state = order[next];
+			let matched = mapping[state].unwrap();
+			if self.states[state].accepting != other.states[matched].accepting {
+				return None;
+			}
+			for (letter,other_letter) in other_index.iter().enumerate() {
+				let (target,other_target) = match (self.states[state].transitions[letter],other.states[matched].transitions[*other_letter]) {
+					(None,None) => continue,
+					(Some(t),Some(o)) => (t,o),
+					_ => return None
+				};
+				match mapping[target] {
+					Some(t) if t == other_target => (),
+					Some(_) => return None,
+					None if used[other_target] => return None,
+					None => {
+						mapping[target] = Some(other_target);
+						used[other_target] = true;
+						order.push(target);
+					}
+				}
+			}
+			next += 1;
+		}
+		return mapping.into_iter().collect();
+	}
+}
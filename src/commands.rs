@@ -0,0 +1,371 @@
+use crate::Automata;
+use crate::ConversionArgs;
+use crate::InputArgs;
+use crate::Rslt;
+use crate::dfa::DFA;
+use crate::error::Error;
+use crate::report::Json;
+use crate::report::Report;
+
+/// The subcommands, each reading the input with the same options as the flags used without a subcommand.
+#[derive(clap::Subcommand)]
+pub enum Command {
+	/// Convert the input to a DFA, an NFA or a regex.
+	Convert(ConvertArgs),
+	/// Test whether a word is in the language of the input.
+	Match(MatchArgs),
+	/// Write the minimal DFA of the input, in canonical form.
+	Minimize(MinimizeArgs),
+	/// Compare the language of the input with the language of the automaton in a .dfa or .nfa file.
+	Compare(CompareArgs),
+	/// Print the sizes of the automata for the input and whether its language is empty or finite.
+	Info(LoadArgs),
+	/// List the words of the language of the input, shortest first.
+	Enumerate(EnumerateArgs),
+	/// Draw the DFA or NFA of the input in the DOT language of Graphviz.
+	Render(RenderArgs),
+	/// Load the input once and explore it interactively: test words, print it as a DFA, NFA or regex, minimize, complement or reverse it, and write it to files.
+	Repl(LoadArgs)
+}
+
+#[derive(clap::Args)]
+pub struct LoadArgs {
+	#[command(flatten)]
+	source: InputArgs,
+
+	#[command(flatten)]
+	conversion: ConversionArgs
+}
+
+impl LoadArgs {
+	fn load(&self) -> Result<Automata,Error> {
+		return Automata::new(&self.source,&self.conversion);
+	}
+}
+
+#[derive(clap::Args)]
+#[command(group(clap::ArgGroup::new("outputs").required(true).multiple(true).args(["dfa_output","nfa_output","regex_output"])))]
+pub struct ConvertArgs {
+	#[command(flatten)]
+	load: LoadArgs,
+
+	/// Location of the DFA file to write the converted input to.
+	#[arg(short, long)]
+	dfa_output: Option<String>,
+
+	/// Location of the NFA file to write the converted input to.
+	#[arg(short, long)]
+	nfa_output: Option<String>,
+
+	/// Print a regex for the input.
+	#[arg(long)]
+	regex_output: bool,
+
+	/// Write the DFA minimized and with its states numbered in breadth first order from the start.
+	#[arg(long)]
+	canonical: bool,
+
+	/// Write the DFA in partial form, leaving out the states that can never reach an accepting state.
+	#[arg(long)]
+	partial_dfa: bool
+}
+
+#[derive(clap::Args)]
+pub struct MatchArgs {
+	#[command(flatten)]
+	load: LoadArgs,
+
+	/// The word to test, which may be empty ("").
+	word: String,
+
+	/// Print each step of the run on the word, and why the word is rejected.
+	#[arg(long)]
+	trace: bool
+}
+
+#[derive(clap::Args)]
+pub struct MinimizeArgs {
+	#[command(flatten)]
+	load: LoadArgs,
+
+	/// Location of the DFA file to write the minimal DFA to. It is printed if not given.
+	#[arg(short, long)]
+	output: Option<String>,
+
+	/// Leave out the sink state, writing the transitions to it as '-'.
+	#[arg(long)]
+	partial_dfa: bool
+}
+
+#[derive(clap::Args)]
+pub struct CompareArgs {
+	#[command(flatten)]
+	load: LoadArgs,
+
+	/// Location of the .dfa or .nfa file to compare with.
+	other: String,
+
+	/// Instead of comparing the languages, report whether the DFA of the input is the same as the DFA in the file up to the numbering of the states.
+	#[arg(long)]
+	isomorphic: bool
+}
+
+#[derive(clap::Args)]
+pub struct EnumerateArgs {
+	#[command(flatten)]
+	load: LoadArgs,
+
+	/// List only the words of at most this length.
+	#[arg(long)]
+	max_length: Option<usize>,
+
+	/// The most words to list.
+	#[arg(long, default_value_t = 100)]
+	limit: usize
+}
+
+#[derive(clap::Args)]
+pub struct RenderArgs {
+	#[command(flatten)]
+	load: LoadArgs,
+
+	/// Draw the NFA instead of the DFA.
+	#[arg(long)]
+	nfa: bool,
+
+	/// Draw the minimal DFA.
+	#[arg(long)]
+	minimize: bool,
+
+	/// Leave out the states of the DFA that can never reach an accepting state.
+	#[arg(long)]
+	partial_dfa: bool,
+
+	/// Location of the file to write the drawing to. It is printed if not given.
+	#[arg(short, long)]
+	output: Option<String>
+}
+
+impl Command {
+	pub fn run(&self, report:&mut Report) -> Rslt {
+		let load = match self {
+			Self::Convert(args) => &args.load,
+			Self::Match(args) => &args.load,
+			Self::Minimize(args) => &args.load,
+			Self::Compare(args) => &args.load,
+			Self::Info(args) => args,
+			Self::Enumerate(args) => &args.load,
+			Self::Render(args) => &args.load,
+			Self::Repl(args) => args
+		};
+		let mut autos = match load.load() {
+			Err(e) => return Rslt::Err(e),
+			Ok(autos) => autos
+		};
+		let result = match self {
+			Self::Convert(args) => convert(&mut autos,args,report),
+			Self::Match(args) => return autos.match_word(&args.word,args.trace,report),
+			Self::Minimize(args) => minimize(&mut autos,args,report),
+			Self::Compare(args) => return match compare(&mut autos,args,report) {
+				Err(e) => Rslt::Err(e),
+				Ok(true) => Rslt::Done,
+				Ok(false) => Rslt::Differ
+			},
+			Self::Info(_) => info(&mut autos,report),
+			Self::Enumerate(args) => enumerate(&mut autos,args,report),
+			Self::Render(args) => render(&mut autos,args,report),
+			Self::Repl(_) => return crate::repl::run(autos)
+		};
+		return match result {
+			Ok(()) => Rslt::Done,
+			Err(e) => Rslt::Err(e)
+		};
+	}
+}
+
+/// Writes the text to the file, or reports it under the key if there is none.
+fn write_or_print(text:String, output:&Option<String>, what:&str, key:&str, report:&mut Report) -> Result<(),Error> {
+	return match output {
+		None => {
+			report.result(key,text.as_str(),text.clone());
+			Ok(())
+		},
+		Some(address) => crate::print_to_file(text,address).map(|()| report.note("output",address.as_str(),format!("{} written to {}",what,address)))
+	};
+}
+
+fn convert(autos:&mut Automata, args:&ConvertArgs, report:&mut Report) -> Result<(),Error> {
+	autos.canonical = args.canonical;
+	autos.partial_dfa = args.partial_dfa;
+	if let Err(e) = autos.report_determinism(report) {
+		return Err(e);
+	}
+	if args.regex_output {
+		if let Err(e) = autos.output_regex(report) {
+			return Err(e);
+		}
+	}
+	if let Some(address) = &args.nfa_output {
+		if let Err(e) = autos.output_nfa(address,report) {
+			return Err(e);
+		}
+	}
+	if let Some(address) = &args.dfa_output {
+		if let Err(e) = autos.output_dfa(address,report) {
+			return Err(e);
+		}
+	}
+	return Ok(());
+}
+
+fn minimize(autos:&mut Automata, args:&MinimizeArgs, report:&mut Report) -> Result<(),Error> {
+	autos.canonical = true;
+	autos.partial_dfa = args.partial_dfa;
+	return match autos.dfa_text() {
+		Err(e) => Err(e),
+		Ok(text) => write_or_print(text,&args.output,"DFA","dfa",report)
+	};
+}
+
+/// Reports whether the languages are equal, or the DFAs isomorphic with --isomorphic, returning whether they are.
+fn compare(autos:&mut Automata, args:&CompareArgs, report:&mut Report) -> Result<bool,Error> {
+	if args.isomorphic {
+		return autos.compare_dfa(&args.other,report);
+	}
+	let other = match crate::read_automaton(&args.other) {
+		Err(e) => return Err(e),
+		Ok(nfa) => match DFA::try_from_nfa(&nfa,&autos.limits) {
+			Err(e) => return Err(Error::from(e)),
+			Ok(dfa) => dfa
+		}
+	};
+	if let Err(e) = autos.ensure_dfa() {
+		return Err(e);
+	}
+	let dfa = autos.dfa.as_ref().unwrap();
+	return match dfa.distinguishing_word(&other) {
+		None => {
+			report.result("equal",true,"The languages are equal.".to_string());
+			Ok(true)
+		},
+		Some(word) => {
+			let (accepted_by,rejected_by) = match dfa.run(&word) {
+				Rslt::Acc => ("the input",args.other.as_str()),
+				_ => (args.other.as_str(),"the input")
+			};
+			report.result("equal",false,format!("The languages differ: {} is accepted by {} but not by {}.",show_word(&word),accepted_by,rejected_by));
+			report.data("distinguishing_word",word.as_str());
+			report.data("accepted_by",accepted_by);
+			Ok(false)
+		}
+	};
+}
+
+fn info(autos:&mut Automata, report:&mut Report) -> Result<(),Error> {
+	if let Some(regex) = &autos.regex {
+		let regex = match regex.to_string_within(&autos.limits) {
+			Ok(r) => r,
+			Err(e) => return Err(Error::from(e))
+		};
+		report.result("regex",regex.as_str(),format!("Regex: {}",regex));
+	}
+	// the DFA is built first, so that it is built as chosen with --dfa-construction rather than from the NFA
+	let input_is_dfa = autos.dfa.is_some();
+	if let Err(e) = autos.ensure_dfa() {
+		return Err(e);
+	}
+	if !input_is_dfa {
+		if let Err(e) = autos.ensure_nfa() {
+			return Err(e);
+		}
+		let nfa = autos.nfa.as_ref().unwrap();
+		let states = nfa.states.len();
+		let accepting = nfa.states.iter().filter(|s| s.accepting).count();
+		let jumps:usize = nfa.states.iter().map(|s| s.transitions[0].0.len()).sum();
+		let transitions:usize = nfa.states.iter().map(|s| s.transitions.iter().map(|t| t.0.len()).sum::<usize>()).sum();
+		report.result("nfa",Json::Object(vec![
+			("states".to_string(),Json::from(states)),
+			("accepting".to_string(),Json::from(accepting)),
+			("transitions".to_string(),Json::from(transitions)),
+			("jumps".to_string(),Json::from(jumps))
+		]),format!("NFA: {} states ({} accepting), {} transitions of which {} are jumps",states,accepting,transitions,jumps));
+	}
+	let dfa = autos.dfa.as_ref().unwrap();
+	report.result("alphabet",dfa.alphabet.as_str(),format!("Alphabet: {}",dfa.alphabet));
+	let states = dfa.states.len();
+	let accepting = dfa.states.iter().filter(|s| s.accepting).count();
+	let complete = dfa.states.iter().all(|s| s.transitions.iter().all(|t| t.is_some()));
+	report.result("dfa",Json::Object(vec![
+		("states".to_string(),Json::from(states)),
+		("accepting".to_string(),Json::from(accepting)),
+		("complete".to_string(),Json::from(complete))
+	]),format!("DFA: {} states ({} accepting), {}",states,accepting,match complete {
+		true => "complete",
+		false => "partial"
+	}));
+	let minimal = dfa.minimize().states.len();
+	report.result("minimal_dfa_states",minimal,format!("Minimal DFA: {} states",minimal));
+	let language = match (dfa.is_empty(),dfa.is_finite()) {
+		(true,_) => "empty",
+		(false,true) => "finite",
+		(false,false) => "infinite"
+	};
+	report.result("language",language,format!("Language: {}",language));
+	let empty_word = matches!(dfa.run(""),Rslt::Acc);
+	report.result("accepts_empty_word",empty_word,format!("Accepts the empty word: {}",match empty_word {
+		true => "yes",
+		false => "no"
+	}));
+	return Ok(());
+}
+
+fn enumerate(autos:&mut Automata, args:&EnumerateArgs, report:&mut Report) -> Result<(),Error> {
+	if let Err(e) = autos.ensure_dfa() {
+		return Err(e);
+	}
+	// one more word than the limit shows whether the list was cut short
+	let mut words = autos.dfa.as_ref().unwrap().words(args.max_length,args.limit.saturating_add(1));
+	let truncated = words.len() > args.limit;
+	words.truncate(args.limit);
+	let text = words.iter().map(|word| show_word(word)).collect::<Vec<String>>().join("\n");
+	match words.is_empty() {
+		true => report.data("words",Vec::<String>::new()),
+		false => report.result("words",words,text)
+	}
+	match truncated {
+		true => report.note("truncated",true,format!("(stopped after {} words, use --limit to list more)",args.limit)),
+		false => report.data("truncated",false)
+	}
+	return Ok(());
+}
+
+fn render(autos:&mut Automata, args:&RenderArgs, report:&mut Report) -> Result<(),Error> {
+	let text = match args.nfa {
+		true => match autos.ensure_nfa() {
+			Err(e) => return Err(e),
+			Ok(()) => autos.nfa.as_ref().unwrap().to_dot()
+		},
+		false => match autos.ensure_dfa() {
+			Err(e) => return Err(e),
+			Ok(()) => {
+				let dfa = match args.minimize {
+					true => autos.dfa.as_ref().unwrap().minimize(),
+					false => autos.dfa.clone().unwrap()
+				};
+				match args.partial_dfa {
+					true => dfa.to_partial().to_dot(),
+					false => dfa.to_complete().to_dot()
+				}
+			}
+		}
+	};
+	return write_or_print(text,&args.output,"Drawing","dot",report);
+}
+
+/// The word as printed in messages, with the empty word shown as ε.
+fn show_word(word:&str) -> String {
+	return match word.is_empty() {
+		true => "ε".to_string(),
+		false => word.to_string()
+	};
+}
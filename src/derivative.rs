@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::StateNum;
+use crate::regex::Regex;
+use crate::regex::RegexTree;
+use crate::dfa::DFA;
+use crate::dfa::DFAState;
+use crate::limits::Limits;
+use crate::limits::LimitError;
+use crate::limits::Progress;
+
+use crate::Index0;
+
+/*
+Brzozowski derivatives. The derivative of a regex r by a letter a is a regex for the words w such that aw is accepted by r, so a word is accepted by r exactly when the derivative of r by each of its letters in turn accepts the empty word.
+
+The derivatives are built with smart constructors that keep them in a normal form: alternatives are flattened, sorted and deduplicated, concatenations are right associated with ε removed, and the empty language (None) is absorbed. This keeps the terms small, and means a regex has finitely many distinct derivatives, so they can be used as the states of a DFA.
+*/
+
+fn boxed(r:RegexTree) -> Box<RegexTree> {
+	return Box::new(r);
+}
+
+fn collect_alternatives(r:RegexTree, alternatives:&mut Vec<RegexTree>) {
+	match r {
+		RegexTree::Or((r1,r2)) => {
+			collect_alternatives(*r1,alternatives);
+			collect_alternatives(*r2,alternatives);
+		},
+		other => alternatives.push(other)
+	}
+}
+
+pub fn make_or(r1:Option<RegexTree>, r2:Option<RegexTree>) -> Option<RegexTree> {
+	let mut alternatives:Vec<RegexTree> = Vec::new();
+	for r in [r1,r2].into_iter().flatten() {
+		collect_alternatives(r,&mut alternatives);
+	}
+	alternatives.sort();
+	alternatives.dedup();
+	let mut result = alternatives.pop();
+	while let Some(a) = alternatives.pop() {
+		result = Some(RegexTree::Or((boxed(a),boxed(result.unwrap()))));
+	}
+	return result;
+}
+
+pub fn make_concat(r1:Option<RegexTree>, r2:Option<RegexTree>) -> Option<RegexTree> {
+	return match (r1,r2) {
+		(None,_) | (_,None) => None,
+		(Some(RegexTree::Concat((x,y))),r) => make_concat(Some(*x),make_concat(Some(*y),r)),
+		(Some(RegexTree::Empty),r) | (r,Some(RegexTree::Empty)) => r,
+		(Some(r1),Some(r2)) => Some(RegexTree::Concat((boxed(r1),boxed(r2))))
+	};
+}
+
+pub fn make_shuffle(r1:Option<RegexTree>, r2:Option<RegexTree>) -> Option<RegexTree> {
+	return match (r1,r2) {
+		(None,_) | (_,None) => None,
+		(Some(RegexTree::Empty),r) | (r,Some(RegexTree::Empty)) => r,
+		(Some(r1),Some(r2)) => Some(RegexTree::Shuffle((boxed(r1),boxed(r2))))
+	};
+}
+
+pub fn make_star(r:RegexTree) -> RegexTree {
+	return match r {
+		RegexTree::Empty => RegexTree::Empty,
+		RegexTree::KleeneStar(_) => r,
+		other => RegexTree::KleeneStar(boxed(other))
+	};
+}
+
+/// r repeated between min and max times, written in the simplest way.
+pub fn make_repeat(r:RegexTree, min:usize, max:Option<usize>) -> RegexTree {
+	return match (min,max) {
+		(_,Some(0)) => RegexTree::Empty,
+		(1,Some(1)) => r,
+		(0,None) => make_star(r),
+		(1,None) => RegexTree::KleenePlus(boxed(r)),
+		(0,Some(1)) => RegexTree::QMark(boxed(r)),
+		_ => RegexTree::Repeat{tree:boxed(r),min,max}
+	};
+}
+
+impl RegexTree {
+	pub fn derivative(&self, letter:Index0) -> Option<RegexTree> {
+		return match self {
+			Self::Empty => None,
+			Self::Single(i) => match *i == letter {
+				true => Some(Self::Empty),
+				false => None
+			},
+			Self::Or((r1,r2)) => make_or(r1.derivative(letter),r2.derivative(letter)),
+			Self::Concat((r1,r2)) => {
+				let first = make_concat(r1.derivative(letter),Some((**r2).clone()));
+				match r1.nullable() {
+					true => make_or(first,r2.derivative(letter)),
+					false => first
+				}
+			},
+			Self::Shuffle((r1,r2)) => make_or(
+				make_shuffle(r1.derivative(letter),Some((**r2).clone())),
+				make_shuffle(Some((**r1).clone()),r2.derivative(letter))
+			),
+			Self::KleeneStar(r) | Self::KleenePlus(r) => make_concat(r.derivative(letter),Some(make_star((**r).clone()))),
+			Self::QMark(r) => r.derivative(letter),
+			Self::Repeat{max:Some(0),..} => None,
+			Self::Repeat{tree,min,max} => {
+				let rest = make_repeat((**tree).clone(),min.saturating_sub(1),max.map(|m| m-1));
+				make_concat(tree.derivative(letter),Some(rest))
+			}
+		};
+	}
+}
+
+impl Regex {
+	/// Whether the regex accepts the word, found by taking derivatives letter by letter without building an automaton.
+	pub fn matches(&self, word:&str) -> bool {
+		let alphabet_map = crate::get_alphabet_hm(&self.alphabet);
+		let mut current = self.tree.clone();
+		for letter in word.chars() {
+			if !alphabet_map.contains_key(&letter) {
+				return false;
+			}
+			current = match current {
+				None => return false,
+				Some(r) => r.derivative(alphabet_map[&letter])
+			};
+		}
+		return match current {
+			None => false,
+			Some(r) => r.nullable()
+		};
+	}
+}
+
+impl DFA {
+	/// Builds a DFA whose states are the distinct derivatives of the regex, as an alternative to converting through an NFA. Stops with an error if it goes over the limits.
+	pub fn from_derivatives(regex:&Regex, limits:&Limits) -> Result<DFA,LimitError> {
+		let budget = limits.start();
+		let num_letters = regex.alphabet.chars().count();
+		let mut new_states:HashMap<Option<RegexTree>,StateNum> = HashMap::new();
+		let mut terms:Vec<Option<RegexTree>> = Vec::new();
+		let mut frontier:VecDeque<StateNum> = VecDeque::new();
+		let mut state_table:Vec<Vec<Option<StateNum>>> = Vec::new();
+
+		let first = make_or(make_concat(regex.tree.clone(),Some(RegexTree::Empty)),None);
+		new_states.insert(first.clone(),0);
+		terms.push(first);
+		frontier.push_back(0);
+		state_table.push(Vec::new());
+		let mut explored = 0;
+		while let Some(current) = frontier.pop_front() {
+			if let Err(e) = budget.check_time(Progress::Determinizing{found:state_table.len(),explored}) {
+				return Err(e);
+			}
+			for letter in 0..num_letters {
+				let next = match &terms[current] {
+					None => None,
+					Some(r) => r.derivative(Index0(letter))
+				};
+				if !new_states.contains_key(&next) {
+					new_states.insert(next.clone(),terms.len());
+					frontier.push_back(terms.len());
+					terms.push(next.clone());
+					state_table.push(Vec::new());
+				}
+				state_table[current].push(Some(new_states[&next]));
+			}
+			explored += 1;
+			if let Err(e) = budget.check_states(state_table.len(),Progress::Determinizing{found:state_table.len(),explored}) {
+				return Err(e);
+			}
+		}
+
+		let states:Vec<DFAState> = (0..state_table.len())
+			.map(|i| DFAState::new(state_table[i].clone(),terms[i].as_ref().is_some_and(|r| r.nullable())))
+			.collect();
+		return Ok(DFA::new(states,regex.alphabet.clone(),0));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::nfa::NFA;
+
+	const CASES:[&str;11] = [
+		"a","ε","\\0","ab|ba","(a|b)*abb","(ab)*|c+","a*b*c*","((a|b)c)*a?","(a{2,3}b)*|c{2,}","(a|ε)(b*|ca)*","ab%c*"
+	];
+
+	#[test]
+	fn same_language_as_thompson() {
+		for regex in CASES {
+			let regex = Regex::parse(regex,Some("abc"),false).unwrap();
+			let expected = DFA::from(&NFA::from(&regex));
+			let derived = DFA::from_derivatives(&regex,&Limits::default()).unwrap();
+			assert_eq!(derived.distinguishing_word(&expected),None,"{}",regex);
+			for word in expected.words(Some(4),100) {
+				assert!(regex.matches(&word),"{} on {:?}",regex,word);
+			}
+			for word in expected.complement().words(Some(4),100) {
+				assert!(!regex.matches(&word),"{} on {:?}",regex,word);
+			}
+		}
+	}
+}
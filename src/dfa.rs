@@ -1,5 +1,8 @@
 use crate::StateNum;
-use crate::Ordered;
+use crate::state_set::StateSet;
+use crate::limits::Limits;
+use crate::limits::LimitError;
+use crate::limits::Progress;
 
 use crate::Rslt;
 use std::fmt;
@@ -15,6 +18,15 @@ use std::convert::TryFrom;
 
 use crate::Index1;
 
+/// How a DFA is built from a regex.
+#[derive(clap::ValueEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum DfaConstruction {
+	/// Convert the regex to an NFA, then use the subset construction.
+	Subset,
+	/// Use the derivatives of the regex as the states.
+	Derivative
+}
+
 #[derive(Clone)]
 pub struct DFA {
     pub states: Vec<DFAState>,
@@ -37,7 +49,10 @@ impl DFA {
 			let equivalent = alphabet_map[&letter];
 			let current_state_obj = &self.states[current_state];
 			let edges = &current_state_obj.transitions;
-			current_state = edges[equivalent.0];
+			current_state = match edges[equivalent.0] {
+				Some(next) => next,
+				None => return Rslt::Rej // the implicit sink state never accepts
+			};
 		}
 		//    println!{"Final state is {}",current_state}
 		return match self.states[current_state].accepting {
@@ -46,6 +61,60 @@ impl DFA {
 		}
 		
     }
+
+	/// The same DFA with the implicit sink state, if any transition goes to it, added as an explicit state.
+	pub fn to_complete(&self) -> DFA {
+		if self.states.iter().all(|s| s.transitions.iter().all(|t| t.is_some())) {
+			return self.clone();
+		}
+		let sink = self.states.len();
+		let mut states:Vec<DFAState> = self.states.iter().map(|s| {
+			DFAState::new(s.transitions.iter().map(|t| Some(t.unwrap_or(sink))).collect(),s.accepting)
+		}).collect();
+		states.push(DFAState::new(vec![Some(sink);self.alphabet.chars().count()],false));
+		return DFA::new(states,self.alphabet.clone(),self.starting);
+	}
+
+	/// The same DFA without the states that cannot reach an accepting state, with transitions to them going to the implicit sink state instead. The starting state is always kept.
+	pub fn to_partial(&self) -> DFA {
+		let num_states = self.states.len();
+		let mut live:Vec<bool> = self.states.iter().map(|s| s.accepting).collect();
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for s in 0..num_states {
+				if !live[s] && self.states[s].transitions.iter().any(|t| t.is_some_and(|t| live[t])) {
+					live[s] = true;
+					changed = true;
+				}
+			}
+		}
+		live[self.starting] = true;
+
+		let mut number:Vec<Option<StateNum>> = vec![None;num_states];
+		let mut kept = 0;
+		for s in 0..num_states {
+			if live[s] {
+				number[s] = Some(kept);
+				kept += 1;
+			}
+		}
+		let states = (0..num_states).filter(|s| live[*s]).map(|s| {
+			let state = &self.states[s];
+			let transitions = state.transitions.iter().map(|t| t.and_then(|t| number[t])).collect();
+			DFAState::new(transitions,state.accepting)
+		}).collect();
+		return DFA::new(states,self.alphabet.clone(),number[self.starting].unwrap());
+	}
+
+	/// Accepts the words over the alphabet that this DFA rejects. The implicit sink state is made explicit first, as it becomes accepting.
+	pub fn complement(&self) -> DFA {
+		let mut result = self.to_complete();
+		for state in &mut result.states {
+			state.accepting = !state.accepting;
+		}
+		return result;
+	}
     
 }
 
@@ -63,7 +132,7 @@ impl TryFrom<Vec<String>> for DFA {
 		let starting = lines[1].parse::<StateNum>().unwrap()-1;   
 		
 		let num_states = lines.len()-2;
-		let num_letters = alphabet.len();
+		let num_letters = alphabet.chars().count();
 		let mut states:Vec<DFAState> = Vec::new();
 		
 		for line in &lines[2..] {
@@ -79,122 +148,87 @@ impl TryFrom<Vec<String>> for DFA {
 
 impl From<&NFA> for DFA {
 	fn from(nfa:&NFA) -> Self {
-		let equivalents:Vec<Ordered> = get_equivalents(&nfa.states);
-		let mut new_states:HashMap<Ordered,StateNum> = HashMap::new();
-		let mut frontier:VecDeque<Ordered> = VecDeque::new();
-		let mut state_table:Vec<Vec<StateNum>> = Vec::new();
+		return match DFA::try_from_nfa(nfa,&Limits::default()) {
+			Ok(dfa) => dfa,
+			Err(e) => unreachable!("no limits were set, but {}",e)
+		};
+	}
+}
+
+impl DFA {
+	/// The subset construction, stopping if the DFA breaks the limits.
+	pub fn try_from_nfa(nfa:&NFA, limits:&Limits) -> Result<DFA,LimitError> {
+		let budget = limits.start();
+		let closures:Vec<StateSet> = get_closures(&nfa.states);
+		let mut new_states:HashMap<StateSet,StateNum> = HashMap::new();
+		let mut frontier:VecDeque<StateSet> = VecDeque::new();
+		let mut state_table:Vec<Vec<Option<StateNum>>> = Vec::new();
 		let mut accepts:Vec<bool> = Vec::new();
 
 		let num_letters = nfa.states[0].transitions.len()-1;
 		
-		let first_state = &equivalents[nfa.starting];
-		add_line_to_table(&nfa.states,&mut new_states,&mut frontier,&mut state_table,&mut accepts,first_state,&equivalents);
-		while !frontier.is_empty() {
-			let current = get_equivalents_vec(&frontier.pop_front().unwrap().0,&equivalents);
-			let current_row = new_states[&current];			
+		let first_state = closures[nfa.starting].clone();
+		add_line_to_table(&nfa.states,&mut new_states,&mut frontier,&mut state_table,&mut accepts,first_state);
+		let mut explored = 0;
+		while let Some(current) = frontier.pop_front() {
+			let progress = Progress::Determinizing{found:state_table.len(),explored};
+			if let Err(e) = budget.check_time(progress) {
+				return Err(e);
+			}
+			let current_row = new_states[&current];
 			for i in 1..(num_letters+1) {
-				let i = Index1(i);
-				let next = get_to_vec(&nfa.states,&current.0,i,&equivalents);
-				if !new_states.contains_key(&next) {
-					add_line_to_table(&nfa.states,&mut new_states,&mut frontier,&mut state_table,&mut accepts,&next,&equivalents);
-				}
-				state_table[current_row].push(new_states[&next]);
+				let next = get_to(&nfa.states,&current,Index1(i),&closures);
+				let next_row = match new_states.get(&next) {
+					Some(row) => *row,
+					None => add_line_to_table(&nfa.states,&mut new_states,&mut frontier,&mut state_table,&mut accepts,next)
+				};
+				state_table[current_row].push(Some(next_row));
+			}
+			explored += 1;
+			if let Err(e) = budget.check_states(state_table.len(),Progress::Determinizing{found:state_table.len(),explored}) {
+				return Err(e);
 			}
 		}
 
 		let states:Vec<DFAState> = (0..state_table.len()).map(|i|DFAState::new(state_table[i].clone(),accepts[i])).collect();
 		let starting = 0;
 		
-		return DFA::new(states,nfa.alphabet.clone(),starting);
-	}
-}
-
-
-fn get_to(states:&Vec<NFAState>, from:StateNum, by:Index1,equivalents:&Vec<Ordered>) -> Ordered {
-	let mut result:Ordered = Ordered(Vec::new());
-	let Ordered(from_states) = &equivalents[from];
-	for state in from_states {
-		let next = &states[*state].transitions[by.0].0;
-		let next_states = get_equivalents_vec(next,equivalents);
-		result = result.join(&next_states);
+		return Ok(DFA::new(states,nfa.alphabet.clone(),starting));
 	}
-	return result;
 }
 
-fn get_to_vec(states:&Vec<NFAState>, from:&Vec<StateNum>, by:Index1, equivalents:&Vec<Ordered>) -> Ordered {
-	let eqs = from.into_iter().map(|state| get_to(states,*state,by,equivalents));
-	let mut result:Ordered = Ordered(Vec::new());
-	for state in eqs {
-		result = result.join(&state);
+/// The states reachable from a set of states by reading the letter, including those reachable with jumps afterwards.
+fn get_to(states:&[NFAState], from:&StateSet, by:Index1, closures:&[StateSet]) -> StateSet {
+	let mut result = StateSet::new(states.len());
+	for state in from.iter() {
+		for next in &states[state].transitions[by.0].0 {
+			if !result.contains(*next) {
+				result.union_with(&closures[*next]);
+			}
 		}
-	return result;
-}
-	
-
-fn get_equivalents_vec(states:&Vec<StateNum>,equivalents:&Vec<Ordered>) -> Ordered {
-	let mut result = Ordered(Vec::new());
-	for state in states {
-		let eqs = &equivalents[*state];
-		result = result.join(eqs);
 	}
 	return result;
 }
 
+/// The ε-closure of each state, found with a depth first search along the jumps.
+fn get_closures(states:&[NFAState]) -> Vec<StateSet> {
+	return (0..states.len())
+		.map(|start| StateSet::reachable(states.len(),&[start],|state| states[state].transitions[0].0.iter().cloned()))
+		.collect();
+}
 
-fn get_equivalents(states:&Vec<NFAState>) -> Vec<Ordered> {		
-	let num_states = states.len();
-	let mut eqs:Vec<Ordered> = (0..num_states)
-		.map(|i| Ordered(vec![i]).join(&states[i].transitions[0])).collect();
-	let mut changed = true;
-	while changed {
-		changed = false;
-		for i in 0..num_states {
-			for j in 0..num_states {
-				if eqs[i].0.contains(&j)  {
-					let v1 = &eqs[i];
-					let v2 = &eqs[j];
-					let new = v1.join(&v2);
-					if v1.0 != new.0 {
-						changed=true;
-						eqs[i]=new;
-					}
-				}
-			}
-		}
-	}
-	return eqs;
-}	
-
-fn add_line_to_table(nfa_states:&Vec<NFAState>,
-					 new_states:&mut HashMap<Ordered,StateNum>,
-					 frontier:&mut VecDeque<Ordered>,
-					 state_table:&mut Vec<Vec<StateNum>>,
+fn add_line_to_table(nfa_states:&[NFAState],
+					 new_states:&mut HashMap<StateSet,StateNum>,
+					 frontier:&mut VecDeque<StateSet>,
+					 state_table:&mut Vec<Vec<Option<StateNum>>>,
 					 accepts:&mut Vec<bool>,
-					 state:&Ordered,
-					 equivalents:&Vec<Ordered>) {
-	new_states.insert(state.clone(),state_table.len());
-	frontier.push_back(state.clone());
-	accepts.push(is_accepting_vec(nfa_states,&state,equivalents));
+					 state:StateSet) -> StateNum {
+	let row = state_table.len();
+	accepts.push(state.iter().any(|s| nfa_states[s].accepting));
 	state_table.push(Vec::new());
-}
-
-fn is_accepting(nfa_states:&Vec<NFAState>,input_state:&StateNum,equivalents:&Vec<Ordered>) -> bool {
-	let Ordered(eqs) = &equivalents[*input_state];
-	for state in eqs {
-		if nfa_states[*state].accepting {
-				return true;
-		}
-	}
-	return false;
-}
-
-fn is_accepting_vec(nfa_states:&Vec<NFAState>,input_states:&Ordered,equivalents:&Vec<Ordered>) -> bool {
-	for state in &input_states.0 {
-		if is_accepting(nfa_states,&state,equivalents) {
-			return true
-		}
-	}
-	return false;
+	new_states.insert(state.clone(),row);
+	frontier.push_back(state);
+	return row;
 }
 
 
@@ -213,28 +247,33 @@ impl fmt::Display for DFA {
     }
 }
 
+/// A state of a DFA. A transition of None goes to an implicit sink state, which is not accepting and never left.
 #[derive(Clone)]
 pub struct DFAState {
-    pub transitions: Vec<StateNum>,
+    pub transitions: Vec<Option<StateNum>>,
     pub accepting:bool
 }
 
 impl DFAState {
-    pub fn new(transitions:Vec<StateNum>,accepting:bool) -> Self {
+    pub fn new(transitions:Vec<Option<StateNum>>,accepting:bool) -> Self {
 		Self{transitions, accepting}
     }
-	fn from_line(line:&String,num_letters:usize,max_state:StateNum) -> Result<Self,String> {
+	fn from_line(line:&str,num_letters:usize,max_state:StateNum) -> Result<Self,String> {
 		let split_state:Vec<&str> = line.split(",").collect();
 		if split_state.len() != num_letters + 1 {
 			return Err("Invalid number of elements on line".to_string());
 		}
 		
-		let mut next_states:Vec<StateNum> = Vec::new();
-		for next_state_str in (&split_state[0..num_letters]).into_iter(){
+		let mut next_states:Vec<Option<StateNum>> = Vec::new();
+		for next_state_str in split_state[0..num_letters].iter(){
+			if next_state_str.is_empty() || *next_state_str == "-" {
+				next_states.push(None);
+				continue;
+			}
 			match next_state_str.parse::<StateNum>() {
 				Ok(next_state_num) => {
 					if next_state_num >= 1 && next_state_num <= max_state {
-						next_states.push(next_state_num-1)
+						next_states.push(Some(next_state_num-1))
 					} else {
 						return Err("Value of next state is outside of the bounds of possible states".to_string());
 					}
@@ -243,11 +282,10 @@ impl DFAState {
 			}
 		}
 		
-		let accept:bool;
-		match split_state[num_letters].parse() {
-			Ok(a) => accept = a,
+		let accept:bool = match split_state[num_letters].parse() {
+			Ok(a) => a,
 			Err(_) => return Err("Poorly formatted accepting/not accepting value.".to_string()),
-		}
+		};
 		
 		return Ok(
 			Self::new(
@@ -260,7 +298,10 @@ impl fmt::Display for DFAState {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let mut output:String=String::new();
 		for transition in &self.transitions {
-			output.push_str(&(transition+1).to_string());
+			match transition {
+				Some(next) => output.push_str(&(next+1).to_string()),
+				None => output.push('-')
+			}
 			output.push(',');
 		}
 		output.push_str(&self.accepting.to_string());
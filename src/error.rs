@@ -0,0 +1,71 @@
+use std::fmt;
+
+use crate::limits::LimitError;
+
+/// What kind of failure stopped the program, which decides its exit code.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ErrorKind {
+	/// The options do not fit together, or ask for something the input does not allow.
+	Usage,
+	/// A file or regex could not be read as an automaton, regex or mapping.
+	Parse,
+	/// The input is valid, but the construction chosen for it cannot handle it, such as the Glushkov construction for a regex with '%'.
+	Unsupported,
+	/// A file could not be read or written.
+	Io,
+	/// A conversion went over a limit set with --max-states, --max-regex-size or --timeout.
+	Limit
+}
+
+impl ErrorKind {
+	pub fn name(&self) -> &'static str {
+		return match self {
+			Self::Usage => "usage",
+			Self::Parse => "parse",
+			Self::Unsupported => "unsupported",
+			Self::Io => "io",
+			Self::Limit => "limit"
+		};
+	}
+}
+
+/// An error of the program, with the kind of failure it is.
+#[derive(Clone,Debug)]
+pub struct Error {
+	pub kind:ErrorKind,
+	pub message:String
+}
+
+impl Error {
+	pub fn new(kind:ErrorKind, message:String) -> Error {
+		return Error{kind,message};
+	}
+
+	pub fn usage(message:String) -> Error {
+		return Error::new(ErrorKind::Usage,message);
+	}
+
+	pub fn parse(message:String) -> Error {
+		return Error::new(ErrorKind::Parse,message);
+	}
+
+	pub fn unsupported(message:String) -> Error {
+		return Error::new(ErrorKind::Unsupported,message);
+	}
+
+	pub fn io(message:String) -> Error {
+		return Error::new(ErrorKind::Io,message);
+	}
+}
+
+impl From<LimitError> for Error {
+	fn from(e:LimitError) -> Self {
+		return Error::new(ErrorKind::Limit,e.to_string());
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+		return write!(f,"{}",self.message);
+	}
+}
@@ -0,0 +1,256 @@
+use crate::StateNum;
+use crate::Ordered;
+use crate::regex::Regex;
+use crate::regex::RegexTree;
+use crate::nfa::NFA;
+use crate::nfa::NFAState;
+
+use crate::error::Error;
+use crate::limits::Budget;
+use crate::limits::Limits;
+use crate::limits::LimitError;
+use crate::limits::Progress;
+
+use crate::Index0;
+use crate::Index1;
+
+/*
+The Glushkov (position) automaton. Each occurrence of a letter in the regex is a position, numbered from 1 in the order they are written, after a bounded repetition r{n,m} is written out as copies of r. The NFA has a state for each position, reached by reading that occurrence of the letter, plus the starting state 0. From the first, last and follow sets of the positions:
+ - state 0 leads to every position that can be read first,
+ - position p leads to every position that can be read directly after it,
+ - the accepting states are the positions that can be read last, and state 0 if the regex accepts the empty word.
+
+A regex is deterministic (one-unambiguous) if its Glushkov automaton is a DFA, i.e. no state leads to two different positions with the same letter.
+*/
+
+/// What the first/last computation knows about a subexpression.
+struct PositionInfo {
+	nullable:bool,
+	first:Vec<StateNum>,
+	last:Vec<StateNum>
+}
+
+struct Positions {
+	letters:Vec<Index0>, // the letter of each position, with a placeholder for state 0
+	follow:Vec<Vec<StateNum>>
+}
+
+fn add_all(to:&mut Vec<StateNum>, from:&Vec<StateNum>) {
+	for p in from {
+		if !to.contains(p) {
+			to.push(*p);
+		}
+	}
+}
+
+/// Writes r{n,m} as the concatenation of its parts, each of which has its own positions.
+fn expand_repeat(r:&RegexTree, min:usize, max:Option<usize>, budget:&Budget) -> Result<RegexTree,LimitError> {
+	return r.repeat_parts(min,max,budget).map(|parts| parts.into_iter()
+		.rev()
+		.reduce(|result,p| RegexTree::Concat((Box::new(p),Box::new(result))))
+		.unwrap_or(RegexTree::Empty));
+}
+
+impl Positions {
+	fn visit(&mut self, r:&RegexTree, budget:&Budget) -> Result<PositionInfo,LimitError> {
+		if let Err(e) = budget.check_time(Progress::Building{states:self.letters.len()}) {
+			return Err(e);
+		}
+		return match r {
+			RegexTree::Empty => Ok(PositionInfo{nullable:true,first:Vec::new(),last:Vec::new()}),
+			RegexTree::Single(i) => {
+				let p = self.letters.len();
+				self.letters.push(*i);
+				self.follow.push(Vec::new());
+				Ok(PositionInfo{nullable:false,first:vec![p],last:vec![p]})
+			},
+			RegexTree::Or((r1,r2)) => {
+				let mut i1 = match self.visit(r1,budget) {
+					Ok(i) => i,
+					Err(e) => return Err(e)
+				};
+				let i2 = match self.visit(r2,budget) {
+					Ok(i) => i,
+					Err(e) => return Err(e)
+				};
+				add_all(&mut i1.first,&i2.first);
+				add_all(&mut i1.last,&i2.last);
+				Ok(PositionInfo{nullable:i1.nullable||i2.nullable,first:i1.first,last:i1.last})
+			},
+			RegexTree::Concat((r1,r2)) => {
+				let i1 = match self.visit(r1,budget) {
+					Ok(i) => i,
+					Err(e) => return Err(e)
+				};
+				let i2 = match self.visit(r2,budget) {
+					Ok(i) => i,
+					Err(e) => return Err(e)
+				};
+				for p in &i1.last {
+					add_all(&mut self.follow[*p],&i2.first);
+				}
+				let mut first = i1.first;
+				if i1.nullable {
+					add_all(&mut first,&i2.first);
+				}
+				let mut last = i2.last;
+				if i2.nullable {
+					add_all(&mut last,&i1.last);
+				}
+				Ok(PositionInfo{nullable:i1.nullable&&i2.nullable,first,last})
+			},
+			RegexTree::KleeneStar(r) => self.visit_loop(r,budget).map(|info| PositionInfo{nullable:true,..info}),
+			RegexTree::KleenePlus(r) => self.visit_loop(r,budget),
+			RegexTree::QMark(r) => self.visit(r,budget).map(|info| PositionInfo{nullable:true,..info}),
+			RegexTree::Repeat{tree,min,max} => match expand_repeat(tree,*min,*max,budget) {
+				Ok(expanded) => self.visit(&expanded,budget),
+				Err(e) => Err(e)
+			},
+			RegexTree::Shuffle(_) => unreachable!("regexes with a shuffle are rejected before their positions are found")
+		};
+	}
+
+	/// Visits the body of a star or plus, where the last positions can be followed by the first ones again.
+	fn visit_loop(&mut self, r:&RegexTree, budget:&Budget) -> Result<PositionInfo,LimitError> {
+		let info = match self.visit(r,budget) {
+			Ok(i) => i,
+			Err(e) => return Err(e)
+		};
+		for p in &info.last {
+			add_all(&mut self.follow[*p],&info.first);
+		}
+		return Ok(info);
+	}
+}
+
+/// Whether the regex uses the shuffle operator, which has no position automaton as interleaved letters do not follow each other in the regex.
+fn has_shuffle(r:&RegexTree) -> bool {
+	return match r {
+		RegexTree::Empty | RegexTree::Single(_) => false,
+		RegexTree::Shuffle(_) => true,
+		RegexTree::KleeneStar(r) | RegexTree::KleenePlus(r) | RegexTree::QMark(r) | RegexTree::Repeat{tree:r,..} => has_shuffle(r),
+		RegexTree::Concat((r1,r2)) | RegexTree::Or((r1,r2)) => has_shuffle(r1) || has_shuffle(r2)
+	};
+}
+
+/// Whether any state leads to two different positions with the same letter.
+fn is_deterministic(positions:&Positions, first:&Vec<StateNum>) -> bool {
+	let check = |targets:&Vec<StateNum>| {
+		let mut letters:Vec<Index0> = targets.iter().map(|p| positions.letters[*p]).collect();
+		letters.sort();
+		letters.dedup();
+		letters.len() == targets.len()
+	};
+	return check(first) && positions.follow.iter().all(check);
+}
+
+impl NFA {
+	/// Builds the Glushkov automaton of the regex, and whether the regex is deterministic.
+	pub fn from_glushkov(regex:&Regex, budget:&Budget) -> Result<(NFA,bool),Error> {
+		let tree = match &regex.tree {
+			None => return Ok((NFA::get_never_accept(regex.alphabet.clone()),true)),
+			Some(t) => t
+		};
+		if has_shuffle(tree) {
+			return Err(Error::unsupported("The Glushkov construction does not support the shuffle operator '%'".to_string()));
+		}
+		let num_letters = regex.alphabet.chars().count();
+		let mut positions = Positions{letters:vec![Index0(0)],follow:vec![Vec::new()]};
+		let info = match positions.visit(tree,budget) {
+			Ok(i) => i,
+			Err(e) => return Err(Error::from(e))
+		};
+
+		let mut states:Vec<NFAState> = Vec::new();
+		for p in 0..positions.letters.len() {
+			let targets = match p {
+				0 => &info.first,
+				_ => &positions.follow[p]
+			};
+			let mut transitions:Vec<Vec<StateNum>> = vec![Vec::new();num_letters+1];
+			for q in targets {
+				let letter:Index1 = positions.letters[*q].into();
+				transitions[letter.0].push(*q);
+			}
+			let transitions:Vec<Ordered> = transitions.into_iter().map(|mut t| {
+				t.sort();
+				Ordered(t)
+			}).collect();
+			let accepting = match p {
+				0 => info.nullable,
+				_ => info.last.contains(&p)
+			};
+			states.push(NFAState::new(transitions,accepting));
+		}
+		let deterministic = is_deterministic(&positions,&info.first);
+		return Ok((NFA::new(states,0,regex.alphabet.clone()),deterministic));
+	}
+}
+
+impl Regex {
+	/// Whether the regex is deterministic (one-unambiguous), meaning its Glushkov automaton is a DFA.
+	pub fn is_deterministic(&self, limits:&Limits) -> Result<bool,Error> {
+		return NFA::from_glushkov(self,&limits.start()).map(|(_,deterministic)| deterministic);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dfa::DFA;
+
+	fn parse(regex:&str) -> Regex {
+		return Regex::parse(regex,Some("abc"),false).unwrap();
+	}
+
+	fn glushkov(regex:&Regex) -> (NFA,bool) {
+		return NFA::from_glushkov(regex,&Limits::default().start()).unwrap();
+	}
+
+	#[test]
+	fn same_language_as_thompson() {
+		let cases = [
+			"a","ε","\\0","ab|ba","(a|b)*abb","(ab)*|c+","a*b*c*","((a|b)c)*a?",
+			"a{3}","a{2,4}b","(ab){1,3}|c{2,}","(a{0,2}b?){2}","(a|ε)(b*|ca)*"
+		];
+		for regex in cases {
+			let regex = parse(regex);
+			let expected = DFA::from(&NFA::from(&regex));
+			assert_eq!(DFA::from(&glushkov(&regex).0).distinguishing_word(&expected),None,"{}",regex);
+		}
+	}
+
+	/// A state for each letter of the regex, with bounded repetitions written out, and one for the start.
+	#[test]
+	fn one_state_per_position() {
+		let cases = [
+			("a",2),
+			("(a|b)*abb",6),
+			("a{3}",4),
+			("a{2,4}b",6),
+			("(ab){1,3}",7),
+			("(a|b){2,}c",8),
+			("(a{2}b){0,2}",7)
+		];
+		for (regex,count) in cases {
+			assert_eq!(glushkov(&parse(regex)).0.states.len(),count,"{}",regex);
+		}
+	}
+
+	#[test]
+	fn deterministic_regexes() {
+		let cases = [
+			("a(b|c)*",true),
+			("(a|b)*a",false),
+			("ab|ac",false),
+			("a?a",false),
+			("a{2}",true),
+			("a{1,2}",true),
+			("a{1,2}a",false),
+			("(ab){1,2}",true)
+		];
+		for (regex,deterministic) in cases {
+			assert_eq!(parse(regex).is_deterministic(&Limits::default()).unwrap(),deterministic,"{}",regex);
+		}
+	}
+}
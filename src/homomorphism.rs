@@ -0,0 +1,182 @@
+use crate::StateNum;
+use crate::Ordered;
+use crate::Index0;
+use crate::Index1;
+use crate::nfa::NFA;
+use crate::nfa::NFAState;
+use crate::dfa::DFA;
+use crate::dfa::DFAState;
+
+use std::convert::TryFrom;
+
+/// A homomorphism, mapping each letter of the source alphabet to a word over the target alphabet.
+pub struct Homomorphism {
+	pub source:String,
+	pub target:String,
+	images:Vec<Vec<Index0>>
+}
+
+/// Reads a mapping file: the first line is the target alphabet, and each further line is `letter:word`, with the word written in the target alphabet (possibly empty).
+impl TryFrom<Vec<String>> for Homomorphism {
+	type Error = String;
+	fn try_from(lines:Vec<String>) -> Result<Self,Self::Error> {
+		if lines.is_empty() {
+			return Err("The mapping file is empty".to_string());
+		}
+		let target = match crate::get_alphabet(&lines[0]) {
+			Err(e) => return Err(e),
+			Ok(ab) => ab
+		};
+		let target_hm = crate::get_alphabet_hm(&target);
+		let mut source = String::new();
+		let mut images:Vec<Vec<Index0>> = Vec::new();
+		for line in &lines[1..] {
+			let (letter,word) = match line.split_once(':') {
+				Some(parts) => parts,
+				None => return Err(format!("Each mapping must be written as letter:word, not {}",line))
+			};
+			let letter:Vec<char> = letter.chars().collect();
+			if letter.len() != 1 {
+				return Err(format!("Each mapping must be for one letter, not {:?}",letter.iter().collect::<String>()));
+			}
+			let letter = letter[0];
+			if letter == ',' || letter == ':' {
+				return Err(format!("The alphabet cannot contain {}",letter));
+			}
+			if source.contains(letter) {
+				return Err(format!("The letter {} is mapped more than once",letter));
+			}
+			let mut image:Vec<Index0> = Vec::new();
+			for c in word.chars() {
+				match target_hm.get(&c) {
+					Some(i) => image.push(*i),
+					None => return Err(format!("The letter {} in the image of {} is not in the target alphabet",c,letter))
+				}
+			}
+			source.push(letter);
+			images.push(image);
+		}
+		return Ok(Homomorphism{source,target,images});
+	}
+}
+
+impl Homomorphism {
+	fn image(&self, letter:char) -> Option<&Vec<Index0>> {
+		return self.source.chars().position(|c| c == letter).map(|i| &self.images[i]);
+	}
+}
+
+impl NFA {
+	/// The image of the language under the homomorphism, replacing each letter edge by a path spelling its image, or a jump for an empty image. Every letter of the alphabet must be mapped.
+	pub fn apply_homomorphism(&self, h:&Homomorphism) -> Result<NFA,String> {
+		let num_letters = h.target.chars().count();
+		let empty = || vec![Ordered(Vec::new());num_letters+1];
+		let mut states:Vec<NFAState> = self.states.iter().map(|s| NFAState::new(empty(),s.accepting)).collect();
+		for (letter_index,letter) in self.alphabet.chars().enumerate() {
+			let image = match h.image(letter) {
+				Some(image) => image,
+				None => return Err(format!("The letter {} is not mapped by the homomorphism",letter))
+			};
+			let by = Index1::from(Index0(letter_index));
+			for from in 0..self.states.len() {
+				for to in &self.states[from].transitions[by.0].0 {
+					add_path(&mut states,from,*to,image,&empty);
+				}
+			}
+		}
+		for (from,s) in self.states.iter().enumerate() {
+			states[from].transitions[0] = states[from].transitions[0].join(&s.transitions[0]);
+		}
+		return Ok(NFA::new(states,self.starting,h.target.clone()));
+	}
+}
+
+/// Adds a path of new states from one state to another, reading the word.
+fn add_path(states:&mut Vec<NFAState>, from:StateNum, to:StateNum, word:&[Index0], empty:&dyn Fn() -> Vec<Ordered>) {
+	let mut current = from;
+	for (position,letter) in word.iter().enumerate() {
+		let next = match position == word.len()-1 {
+			true => to,
+			false => {
+				states.push(NFAState::new(empty(),false));
+				states.len()-1
+			}
+		};
+		let by = Index1::from(*letter);
+		states[current].transitions[by.0] = states[current].transitions[by.0].join(&Ordered(vec![next]));
+		current = next;
+	}
+	if word.is_empty() {
+		states[from].transitions[0] = states[from].transitions[0].join(&Ordered(vec![to]));
+	}
+}
+
+impl DFA {
+	/// The inverse image of the language under the homomorphism, over its source alphabet: each letter moves to where the DFA goes reading the image of the letter. Letters of an image outside the alphabet of the DFA go to the implicit sink state.
+	pub fn inverse_homomorphism(&self, h:&Homomorphism) -> DFA {
+		let alphabet_hm = crate::get_alphabet_hm(&self.alphabet);
+		let target:Vec<char> = h.target.chars().collect();
+		let run_image = |from:StateNum, image:&Vec<Index0>| -> Option<StateNum> {
+			let mut current = from;
+			for letter in image {
+				let index = match alphabet_hm.get(&target[letter.0]) {
+					Some(i) => *i,
+					None => return None
+				};
+				current = match self.states[current].transitions[index.0] {
+					Some(next) => next,
+					None => return None
+				};
+			}
+			return Some(current);
+		};
+		let states = (0..self.states.len()).map(|s| {
+			DFAState::new(h.images.iter().map(|image| run_image(s,image)).collect(),self.states[s].accepting)
+		}).collect();
+		return DFA::new(states,h.source.clone(),self.starting);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::regex::Regex;
+
+	/// a -> xy, b -> ε, c -> yyx
+	fn h() -> Homomorphism {
+		let lines = ["xy","a:xy","b:","c:yyx"];
+		return Homomorphism::try_from(lines.iter().map(|l| l.to_string()).collect::<Vec<String>>()).unwrap();
+	}
+
+	fn dfa(regex:&str, alphabet:&str) -> DFA {
+		return DFA::from(&NFA::from(&Regex::parse(regex,Some(alphabet),false).unwrap()));
+	}
+
+	#[test]
+	fn images() {
+		let cases = [
+			("(ab)*","(xy)*"),
+			("(a|c)b*","xy|yyx"),
+			("b*","ε"),
+			("ca+","yyx(xy)+")
+		];
+		for (regex,image) in cases {
+			let result = NFA::from(&Regex::parse(regex,Some("abc"),false).unwrap()).apply_homomorphism(&h()).unwrap();
+			assert_eq!(DFA::from(&result).distinguishing_word(&dfa(image,"xy")),None,"{}",regex);
+		}
+	}
+
+	#[test]
+	fn inverse_images() {
+		let cases = [
+			("(xy)*","[ab]*"),
+			("xyyyx","b*ab*cb*"),
+			("y*","b*"),
+			("x(yx)*y","b*a[ab]*")
+		];
+		for (regex,inverse) in cases {
+			let result = dfa(regex,"xy").inverse_homomorphism(&h());
+			assert_eq!(result.distinguishing_word(&dfa(inverse,"abc")),None,"{}",regex);
+		}
+	}
+}
@@ -6,23 +6,246 @@ use crate::nfa::NFAState;
 
 use crate::Index1;
 
-pub fn nfa_to_regex(nfa:&NFA) -> Regex {
-	let alphabet:String=nfa.alphabet.clone();
+use crate::limits::Budget;
+use crate::limits::Limits;
+use crate::limits::LimitError;
+use crate::limits::Progress;
+
+type Table = Vec<Vec<Option<RegexTree>>>;
+
+/// The order in which states are eliminated when converting an automaton to a regex. The order does not change the language of the regex, but can change its length enormously.
+#[derive(clap::ValueEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum EliminationOrder {
+	/// States in the order they are numbered.
+	Index,
+	/// The state with the fewest paths through it (incoming edges times outgoing edges) first.
+	Edges,
+	/// The state whose elimination adds the least to the size of the regex first (Delgado and Morais' heuristic).
+	Weight,
+	/// Every possible order, for automata with at most 6 states; for larger automata this is the same as weight.
+	Exhaustive,
+	/// Whichever of the orders above gives the shortest regex. Index and exhaustive are only tried for automata with at most 6 states.
+	Shortest
+}
+
+/// The algorithm used to convert an automaton to a regex.
+#[derive(clap::ValueEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum RegexAlgorithm {
+	/// State elimination, in the chosen elimination order.
+	Elimination,
+	/// Solving the equations of the automaton with Arden's lemma.
+	Arden,
+	/// Whichever of the algorithms above gives the shorter regex.
+	Shortest
+}
+
+/// Automata with more states than this are not searched exhaustively, or eliminated in index order when looking for the shortest regex.
+const EXHAUSTIVE_LIMIT:usize = 6;
+
+#[derive(Clone,Copy,Debug)]
+pub struct RegexOptions {
+	pub simplify:bool,
+	pub algorithm:RegexAlgorithm,
+	pub order:EliminationOrder
+}
+
+impl Default for RegexOptions {
+	fn default() -> Self {
+		Self{simplify:true,algorithm:RegexAlgorithm::Elimination,order:EliminationOrder::Weight}
+	}
+}
+
+/// One way of converting an automaton to a regex.
+enum Method {
+	Arden,
+	InOrder(Vec<StateNum>),
+	Greedy(fn(&Table,&[bool],StateNum) -> usize)
+}
+
+/// Converts with the chosen algorithm and order, stopping if any of them breaks the limits.
+pub fn convert(nfa:&NFA, options:&RegexOptions, limits:&Limits) -> Result<Regex,LimitError> {
+	return convert_within(nfa,options,&limits.start());
+}
+
+/// Tries each method in turn, abandoning a method as soon as its regex grows as large as the shortest found so far, and simplifies only the shortest.
+fn convert_within(nfa:&NFA, options:&RegexOptions, budget:&Budget) -> Result<Regex,LimitError> {
+	let mut best:Option<Regex> = None;
+	for method in get_methods(nfa,options) {
+		let bound = best.as_ref().map(regex_size);
+		let candidate = match method {
+			Method::Arden => crate::arden::nfa_to_regex_arden(nfa,bound,budget),
+			Method::InOrder(order) => eliminate_in_order(nfa,&order,bound,budget),
+			Method::Greedy(cost) => eliminate_greedily(nfa,cost,bound,budget)
+		};
+		match candidate {
+			Ok(Some(r)) => best = Some(r),
+			Ok(None) => (),
+			Err(e) => return Err(e)
+		}
+	}
+	let best = best.expect("there is always at least one method");
+	return match options.simplify {
+		true => best.simplify(budget),
+		false => Ok(best)
+	};
+}
+
+fn get_methods(nfa:&NFA, options:&RegexOptions) -> Vec<Method> {
+	let mut methods = Vec::new();
+	if options.algorithm != RegexAlgorithm::Arden {
+		let num_states = nfa.states.len();
+		let small = num_states <= EXHAUSTIVE_LIMIT;
+		match options.order {
+			EliminationOrder::Index => methods.push(Method::InOrder((0..num_states).collect())),
+			EliminationOrder::Edges => methods.push(Method::Greedy(edges_cost)),
+			EliminationOrder::Weight => methods.push(Method::Greedy(weight_cost)),
+			EliminationOrder::Exhaustive if small => methods.extend(get_permutations(num_states).into_iter().map(Method::InOrder)),
+			EliminationOrder::Exhaustive => methods.push(Method::Greedy(weight_cost)),
+			EliminationOrder::Shortest => {
+				// the heuristics first, so the other orders can be abandoned early
+				methods.push(Method::Greedy(weight_cost));
+				methods.push(Method::Greedy(edges_cost));
+				if small {
+					methods.extend(get_permutations(num_states).into_iter().map(Method::InOrder));
+				}
+			}
+		}
+	}
+	if options.algorithm != RegexAlgorithm::Elimination {
+		methods.push(Method::Arden);
+	}
+	return methods;
+}
+
+fn regex_size(regex:&Regex) -> usize {
+	return match &regex.tree {
+		None => 0,
+		Some(tree) => tree.size()
+	};
+}
+
+fn get_permutations(n:usize) -> Vec<Vec<StateNum>> {
+	if n == 0 {
+		return vec![Vec::new()];
+	}
+	let mut result = Vec::new();
+	for smaller in get_permutations(n-1) {
+		for position in 0..n {
+			let mut p = smaller.clone();
+			p.insert(position,n-1);
+			result.push(p);
+		}
+	}
+	return result;
+}
+
+/// Eliminates the states in the given order. Returns None once a regex between the remaining states is as large as the bound.
+fn eliminate_in_order(nfa:&NFA, order:&[StateNum], bound:Option<usize>, budget:&Budget) -> Result<Option<Regex>,LimitError> {
 	let mut table = get_2d_array(nfa);
-	for i in 0..(table.len()-1) {
-		bypass_state(&mut table,i);
+	let mut removed = vec![false;table.len()];
+	for (eliminated,i) in order.iter().enumerate() {
+		match check_budget(&table,&removed,eliminated,bound,budget) {
+			Ok(true) => (),
+			Ok(false) => return Ok(None),
+			Err(e) => return Err(e)
+		}
+		bypass_state(&mut table,&removed,*i);
+		removed[*i] = true;
 	}
-	let rt = table.last().unwrap().last().unwrap();
-	return Regex::new(alphabet,rt.clone());
+	return read_result(nfa,&table,&removed,order.len(),bound,budget);
 }
 
-fn bypass_state(table:&mut Vec<Vec<Option<RegexTree>>>,line:StateNum) {
-	//all lines before this one have already been removed so can be ignored.
+/// Repeatedly eliminates the remaining state the cost function rates cheapest. Returns None once a regex between the remaining states is as large as the bound.
+fn eliminate_greedily(nfa:&NFA, cost:fn(&Table,&[bool],StateNum) -> usize, bound:Option<usize>, budget:&Budget) -> Result<Option<Regex>,LimitError> {
+	let mut table = get_2d_array(nfa);
+	let mut removed = vec![false;table.len()];
+	for eliminated in 0..nfa.states.len() {
+		match check_budget(&table,&removed,eliminated,bound,budget) {
+			Ok(true) => (),
+			Ok(false) => return Ok(None),
+			Err(e) => return Err(e)
+		}
+		let next = (0..nfa.states.len())
+			.filter(|i| !removed[*i])
+			.min_by_key(|i| cost(&table,&removed,*i))
+			.unwrap();
+		bypass_state(&mut table,&removed,next);
+		removed[next] = true;
+	}
+	return read_result(nfa,&table,&removed,nfa.states.len(),bound,budget);
+}
+
+/// Checks the time taken and the largest regex between the states that are left. Returns false if that regex is as large as the bound, as the final regex can then be no shorter.
+fn check_budget(table:&Table, removed:&[bool], eliminated:usize, bound:Option<usize>, budget:&Budget) -> Result<bool,LimitError> {
+	let progress = Progress::Eliminating{eliminated,total:removed.len()-1};
+	if let Err(e) = budget.check_time(progress) {
+		return Err(e);
+	}
+	let largest = || (0..table.len())
+		.filter(|i| !removed[*i])
+		.flat_map(|i| table[i].iter().flatten().map(|r| r.size()))
+		.max()
+		.unwrap_or(0);
+	if let Some(bound) = bound {
+		if largest() >= bound {
+			return Ok(false);
+		}
+	}
+	return budget.check_regex_size(largest,progress).map(|_| true);
+}
+
+fn read_result(nfa:&NFA, table:&Table, removed:&[bool], eliminated:usize, bound:Option<usize>, budget:&Budget) -> Result<Option<Regex>,LimitError> {
+	return match check_budget(table,removed,eliminated,bound,budget) {
+		Ok(true) => {
+			let rt = table.last().unwrap().last().unwrap();
+			Ok(Some(Regex::new(nfa.alphabet.clone(),rt.clone())))
+		},
+		Ok(false) => Ok(None),
+		Err(e) => Err(e)
+	};
+}
+
+/// The sizes of the regexes on the edges into, out of, and looping on a state, ignoring removed states.
+fn get_edges(table:&Table, removed:&[bool], line:StateNum) -> (Vec<usize>,Vec<usize>,usize) {
+	let size = |r:&Option<RegexTree>| r.as_ref().map(|r| r.size());
+	let incoming = (0..table.len())
+		.filter(|p| *p != line && !removed[*p])
+		.filter_map(|p| size(&table[p][line]))
+		.collect();
+	let outgoing = (0..table[line].len())
+		.filter(|p| *p != line && (*p >= removed.len() || !removed[*p]))
+		.filter_map(|p| size(&table[line][p]))
+		.collect();
+	let self_loop = size(&table[line][line]).unwrap_or(0);
+	return (incoming,outgoing,self_loop);
+}
+
+fn edges_cost(table:&Table, removed:&[bool], line:StateNum) -> usize {
+	let (incoming,outgoing,_) = get_edges(table,removed,line);
+	return incoming.len() * outgoing.len();
+}
+
+fn weight_cost(table:&Table, removed:&[bool], line:StateNum) -> usize {
+	// the size added to the regex by bypassing the state, as each incoming edge is copied once per outgoing edge and vice versa
+	let (incoming,outgoing,self_loop) = get_edges(table,removed,line);
+	let num_in = incoming.len();
+	let num_out = outgoing.len();
+	let in_weight:usize = incoming.iter().sum::<usize>() * num_out.saturating_sub(1);
+	let out_weight:usize = outgoing.iter().sum::<usize>() * num_in.saturating_sub(1);
+	let loop_weight = self_loop * (num_in * num_out).saturating_sub(1);
+	return in_weight + out_weight + loop_weight;
+}
+
+fn bypass_state(table:&mut Table,removed:&[bool],line:StateNum) {
+	//states that have already been removed can be ignored, as nothing leads to them anymore.
 
 	let self_loop = table[line][line].clone();
 	let leaving = get_leaving(table[line].clone(),line);
 
-	for state in (line+1)..table.len() {
+	for state in 0..table.len() {
+		if state == line || removed[state] {
+			continue;
+		}
 		if let Some(original) = &table[state][line] {
 			//does go to state we're removing
 			let start:RegexTree = match &self_loop {
@@ -48,12 +271,11 @@ fn bypass_state(table:&mut Vec<Vec<Option<RegexTree>>>,line:StateNum) {
 		}
 	}
 }
-
 fn get_leaving(line:Vec<Option<RegexTree>>,num:StateNum) -> Vec<(RegexTree,StateNum)> {
 	let mut result = Vec::new();
-	for i in 0..line.len() {
+	for (i,cell) in line.iter().enumerate() {
 		if i!=num {
-			if let Some(rt) = &line[i] {
+			if let Some(rt) = cell {
 				result.push((rt.clone(),i));
 			}
 		} 
@@ -68,7 +290,7 @@ fn get_2d_array(nfa:&NFA) -> Vec<Vec<Option<RegexTree>>> {
 	let mut new_start:Vec<Option<RegexTree>> = vec![None;size];
 	new_start[nfa.starting]=Some(RegexTree::Empty);
 
-	let mut result:Vec<Vec<Option<RegexTree>>> = (&nfa.states).into_iter().map(|s| get_1d_array(&s,size)).collect();
+	let mut result:Vec<Vec<Option<RegexTree>>> = nfa.states.iter().map(|s| get_1d_array(s,size)).collect();
 	result.push(new_start);
 	return result;
 }
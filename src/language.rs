@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::StateNum;
+use crate::dfa::DFA;
+
+/*
+Properties of the language of a DFA. Most are found on the minimal DFA without its sink state, in which every state is reachable and, if the language is not empty, every state can reach an accepting state, so every path in it can be extended to an accepted word.
+*/
+
+impl DFA {
+	fn trimmed(&self) -> DFA {
+		return self.minimize().to_partial();
+	}
+
+	/// Whether the DFA accepts no words at all.
+	pub fn is_empty(&self) -> bool {
+		return !self.minimize().states.iter().any(|s| s.accepting);
+	}
+
+	/// Whether the DFA accepts finitely many words, which is when no cycle can be extended to an accepted word.
+	pub fn is_finite(&self) -> bool {
+		if self.is_empty() {
+			return true;
+		}
+		let dfa = self.trimmed();
+		// 0 is unvisited, 1 is on the current path and 2 is finished
+		let mut colour = vec![0;dfa.states.len()];
+		let mut stack:Vec<(StateNum,usize)> = vec![(dfa.starting,0)];
+		colour[dfa.starting] = 1;
+		while let Some((state,letter)) = stack.pop() {
+			if letter == dfa.states[state].transitions.len() {
+				colour[state] = 2;
+				continue;
+			}
+			stack.push((state,letter+1));
+			if let Some(next) = dfa.states[state].transitions[letter] {
+				match colour[next] {
+					0 => {
+						colour[next] = 1;
+						stack.push((next,0));
+					},
+					1 => return false,
+					_ => ()
+				}
+			}
+		}
+		return true;
+	}
+
+	/// The words of the language in order of length and then alphabetically, up to the given length and at most limit of them.
+	pub fn words(&self, max_length:Option<usize>, limit:usize) -> Vec<String> {
+		let mut result:Vec<String> = Vec::new();
+		if self.is_empty() {
+			return result;
+		}
+		let dfa = self.trimmed();
+		// a word of a finite language is shorter than the number of states, as it visits no state twice
+		let max_length = match self.is_finite() {
+			true => Some(max_length.unwrap_or(usize::MAX).min(dfa.states.len()-1)),
+			false => max_length
+		};
+		// ends[k] holds the states from which an accepting state is k letters away
+		let mut ends:Vec<Vec<bool>> = vec![dfa.states.iter().map(|s| s.accepting).collect()];
+		let mut length = 0;
+		while result.len() < limit && max_length.is_none_or(|max| length <= max) {
+			if length == ends.len() {
+				let previous = &ends[length-1];
+				let next = dfa.states.iter().map(|s| s.transitions.iter().any(|t| t.is_some_and(|t| previous[t]))).collect();
+				ends.push(next);
+			}
+			collect_words(&dfa,&ends,dfa.starting,length,&mut String::new(),&mut result,limit);
+			length += 1;
+		}
+		return result;
+	}
+
+	/// The shortest word, first alphabetically, accepted by exactly one of the DFAs, or None if they accept the same language. Letters in only one alphabet go to the implicit sink state of the other DFA.
+	pub fn distinguishing_word(&self, other:&DFA) -> Option<String> {
+		let first = self.extend_alphabet(&other.alphabet);
+		let second = other.extend_alphabet(&self.alphabet);
+		let mut letters:Vec<(char,usize,usize)> = first.alphabet.chars().enumerate()
+			.map(|(i,c)| (c,i,second.alphabet.chars().position(|o| o == c).unwrap()))
+			.collect();
+		letters.sort();
+		let accepting = |dfa:&DFA, state:Option<StateNum>| state.is_some_and(|s| dfa.states[s].accepting);
+
+		type Pair = (Option<StateNum>,Option<StateNum>);
+		let start:Pair = (Some(first.starting),Some(second.starting));
+		let mut previous:HashMap<Pair,Option<(Pair,char)>> = HashMap::new();
+		previous.insert(start,None);
+		let mut queue:VecDeque<Pair> = VecDeque::from([start]);
+		while let Some(pair) = queue.pop_front() {
+			if accepting(&first,pair.0) != accepting(&second,pair.1) {
+				let mut word:Vec<char> = Vec::new();
+				let mut current = pair;
+				while let Some((before,letter)) = previous[&current] {
+					word.push(letter);
+					current = before;
+				}
+				return Some(word.into_iter().rev().collect());
+			}
+			for (letter,i,j) in &letters {
+				let next:Pair = (pair.0.and_then(|s| first.states[s].transitions[*i]),pair.1.and_then(|s| second.states[s].transitions[*j]));
+				if next != (None,None) && !previous.contains_key(&next) {
+					previous.insert(next,Some((pair,*letter)));
+					queue.push_back(next);
+				}
+			}
+		}
+		return None;
+	}
+}
+
+/// Adds the words of exactly the remaining length that lead from the state to an accepting state, in alphabetical order.
+fn collect_words(dfa:&DFA, ends:&Vec<Vec<bool>>, state:StateNum, remaining:usize, word:&mut String, result:&mut Vec<String>, limit:usize) {
+	if result.len() == limit || !ends[remaining][state] {
+		return;
+	}
+	if remaining == 0 {
+		result.push(word.clone());
+		return;
+	}
+	for (letter,c) in dfa.alphabet.chars().enumerate() {
+		if let Some(next) = dfa.states[state].transitions[letter] {
+			word.push(c);
+			collect_words(dfa,ends,next,remaining-1,word,result,limit);
+			word.pop();
+		}
+	}
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Limits on the conversions that can take exponential time or space. None is no limit.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct Limits {
+	/// The most states a DFA built by the subset or derivative construction may have.
+	pub max_states:Option<usize>,
+	/// The largest size (number of nodes) a regex may reach while converting an automaton to a regex, or when a bounded repetition is written out as copies.
+	pub max_regex_size:Option<usize>,
+	/// The longest a single conversion, simplification or printing of a regex may take.
+	pub timeout:Option<Duration>
+}
+
+impl Limits {
+	/// Starts the clock for one conversion.
+	pub fn start(&self) -> Budget {
+		return Budget{limits:*self,started:Instant::now()};
+	}
+}
+
+/// How far a conversion got before it was stopped.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Progress {
+	Determinizing{found:usize, explored:usize},
+	Eliminating{eliminated:usize, total:usize},
+	Solving{solved:usize, total:usize},
+	Expanding{copies:usize},
+	Building{states:usize},
+	Simplifying{passes:usize},
+	Printing
+}
+
+impl fmt::Display for Progress {
+	fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			Self::Determinizing{found,explored} => write!(f,"while building the DFA, with {} states found and {} of them explored",found,explored),
+			Self::Eliminating{eliminated,total} => write!(f,"while eliminating states, with {} of {} eliminated",eliminated,total),
+			Self::Solving{solved,total} => write!(f,"while solving the equations of the automaton, with {} of {} solved",solved,total),
+			Self::Expanding{copies} => write!(f,"while writing out a bounded repetition as {} copies",copies),
+			Self::Building{states} => write!(f,"while building the NFA, with {} states so far",states),
+			Self::Simplifying{passes} => write!(f,"while simplifying the regex, after {} passes",passes),
+			Self::Printing => write!(f,"while printing the regex")
+		};
+	}
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum LimitError {
+	TooManyStates{limit:usize, progress:Progress},
+	RegexTooLarge{limit:usize, size:usize, progress:Progress},
+	Timeout{limit:Duration, progress:Progress}
+}
+
+impl fmt::Display for LimitError {
+	fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			Self::TooManyStates{limit,progress} => write!(f,"The DFA has more than the limit of {} states, stopped {}",limit,progress),
+			Self::RegexTooLarge{limit,size,progress} => write!(f,"The regex reached size {}, more than the limit of {}, stopped {}",size,limit,progress),
+			Self::Timeout{limit,progress} => write!(f,"Timed out after {:?}, stopped {}",limit,progress)
+		};
+	}
+}
+
+/// The limits of one conversion, with the time it started.
+#[derive(Clone,Copy,Debug)]
+pub struct Budget {
+	limits:Limits,
+	started:Instant
+}
+
+impl Budget {
+	pub fn check_time(&self, progress:Progress) -> Result<(),LimitError> {
+		return match self.limits.timeout {
+			Some(limit) if self.started.elapsed() > limit => Err(LimitError::Timeout{limit,progress}),
+			_ => Ok(())
+		};
+	}
+
+	pub fn check_states(&self, found:usize, progress:Progress) -> Result<(),LimitError> {
+		return match self.limits.max_states {
+			Some(limit) if found > limit => Err(LimitError::TooManyStates{limit,progress}),
+			_ => Ok(())
+		};
+	}
+
+	/// Checks the size of a regex, which is only computed if there is a limit.
+	pub fn check_regex_size(&self, size:impl FnOnce() -> usize, progress:Progress) -> Result<(),LimitError> {
+		let limit = match self.limits.max_regex_size {
+			None => return Ok(()),
+			Some(limit) => limit
+		};
+		let size = size();
+		return match size > limit {
+			true => Err(LimitError::RegexTooLarge{limit,size,progress}),
+			false => Ok(())
+		};
+	}
+}
@@ -1,76 +1,229 @@
+// Functions end in an explicit `return` and match on results rather than using `?`,
+// and the automaton types keep their conventional upper-case names.
+#![allow(clippy::needless_return, clippy::question_mark, clippy::upper_case_acronyms)]
+
 mod dfa;
 mod nfa;
 mod regex;
+mod regex_parser;
+mod regex_simplify;
 mod int_nfa_reg;
+mod arden;
+mod derivative;
+mod partial_derivative;
+mod glushkov;
+mod state_set;
+mod limits;
+mod canonical;
+mod operations;
+mod transform;
+mod quotient;
+mod homomorphism;
+mod alphabet;
+mod trace;
+mod repl;
+mod commands;
+mod language;
+mod render;
+mod error;
+mod report;
 
 use crate::dfa::DFA;
+use crate::dfa::DfaConstruction;
 use crate::nfa::NFA;
+use crate::nfa::NfaConstruction;
 use crate::regex::Regex;
+use crate::int_nfa_reg::EliminationOrder;
+use crate::int_nfa_reg::RegexAlgorithm;
+use crate::int_nfa_reg::RegexOptions;
+use crate::limits::Limits;
+use crate::transform::Transform;
+use crate::trace::Trace;
+use crate::commands::Command;
+use crate::error::Error;
+use crate::error::ErrorKind;
+use crate::report::Format;
+use crate::report::Json;
+use crate::report::Report;
 
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
+use std::process::ExitCode;
+use std::time::Duration;
 
 use std::convert::From;
 
 use clap::Parser; //allows me flexibility with reading commandline arguments
+use clap::CommandFactory;
+use clap::FromArgMatches;
+use clap::parser::ValueSource;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// Location of the file storing the finite automaton (.dfa or .nfa).
-    #[arg(short,long)]
-    input: Option<String>,
+	#[command(subcommand)]
+	command: Option<Command>,
+
+	#[command(flatten)]
+	source: InputArgs,
+
+	#[command(flatten)]
+	conversion: ConversionArgs,
 
-    /// Regular expression to be evaluated or converted. ':' and ',' are not valid. () is used to alter order of operations, + is any positive number of repetitions, * is + but also zero repetitions, ? is zero or one repetitions, | is or.
-    #[arg(short, long)]
-    regex: Option<String>,
+	/// The word to be validated.
+	#[arg(short, long)]
+	word: Option<String>,
 
-    /// The word to be validated.
-    #[arg(short, long)]
-    word: Option<String>,
+	/// Print each step of the run on the word: the state after each letter for a DFA, or the set of active states with the jumps followed for an NFA, and why the word is rejected.
+	#[arg(long)]
+	trace: bool,
 
-    /// Location of the DFA file to write the converted input to.
-    #[arg(short, long)]
-    dfa_output: Option<String>,
+	/// Location of the DFA file to write the converted input to.
+	#[arg(short, long)]
+	dfa_output: Option<String>,
 
-    /// Location of the NFA file to write the converted input to.
-    #[arg(short, long)]
-    nfa_output: Option<String>,
+	/// Location of the NFA file to write the converted input to.
+	#[arg(short, long)]
+	nfa_output: Option<String>,
 
 	/// Flag if a converted regex is desired.
 	#[arg(long)]
 	regex_output: bool,
+
+	/// Write the DFA minimized and with its states numbered in breadth first order from the start, so that DFAs for the same language give identical files.
+	#[arg(long)]
+	canonical: bool,
+
+	/// Write the DFA in partial form, leaving out the states that can never reach an accepting state and writing the transitions to them as '-'.
+	#[arg(long)]
+	partial_dfa: bool,
+
+	/// Location of a DFA file to compare with the DFA of the input, reporting whether they are the same up to the numbering of the states.
+	#[arg(long)]
+	isomorphic_to: Option<String>,
+
+	/// Print only the results asked for, leaving out messages such as where files were written.
+	#[arg(short, long, global = true)]
+	quiet: bool,
+
+	/// Print the results as lines of text, or as one JSON object including any error.
+	#[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+	format: Format,
+}
+
+/// Where the language comes from, shared by the subcommands.
+#[derive(clap::Args)]
+struct InputArgs {
+	/// Location of the file storing the finite automaton (.dfa or .nfa).
+	#[arg(short,long)]
+	input: Option<String>,
+
+	/// Regular expression to be evaluated or converted. ':' and ',' are not valid. () is used to alter order of operations, + is any positive number of repetitions, * is + but also zero repetitions, ? is zero or one repetitions, | is or.
+	#[arg(short, long)]
+	regex: Option<String>,
+
+	/// Alphabet of the regex given with --regex. Defaults to the letters appearing in the regex; letters of the regex outside the declared alphabet are an error.
+	#[arg(short, long)]
+	alphabet: Option<String>,
+
+	/// Accept malformed regexes, interpreting them as earlier versions did (e.g. a missing operand of '|' or '*' is read as the empty word) instead of reporting a syntax error.
+	#[arg(long)]
+	lenient: bool,
+
+	/// Transform the language of the input before anything else is done, applied in the order given: concat=FILE, union=FILE and shuffle=FILE combine it with the automaton in a .dfa or .nfa file, star, plus and optional apply the regex operator to it, prefix, suffix and infix take all prefixes, suffixes or factors of its words, right-quotient=FILE and left-quotient=FILE take its quotient by the language of the file, hom=FILE and inverse-hom=FILE apply the homomorphism in a mapping file or its inverse, extend=LETTERS and restrict=LETTERS add letters to or keep only some letters of the alphabet, rename=a:x,b:y renames letters and project=LETTERS erases letters from the words.
+	#[arg(short, long, value_parser = Transform::parse)]
+	transform: Vec<Transform>,
+}
+
+/// How the language is converted between DFAs, NFAs and regexes, shared by the subcommands.
+#[derive(clap::Args)]
+struct ConversionArgs {
+	/// Construction used to convert a regex to an NFA.
+	#[arg(long, value_enum, default_value_t = NfaConstruction::Thompson)]
+	nfa_construction: NfaConstruction,
+
+	/// Construction used to convert a regex to a DFA.
+	#[arg(long, value_enum, default_value_t = DfaConstruction::Subset)]
+	dfa_construction: DfaConstruction,
+
+	/// Algorithm used to convert to a regex.
+	#[arg(long, value_enum, default_value_t = RegexAlgorithm::Elimination)]
+	regex_algorithm: RegexAlgorithm,
+
+	/// Order in which states are eliminated when converting to a regex.
+	#[arg(long, value_enum, default_value_t = EliminationOrder::Weight)]
+	elimination_order: EliminationOrder,
+
+	/// Output the regex produced by converting the automaton without simplifying it.
+	#[arg(long)]
+	raw_regex: bool,
+
+	/// Stop converting to a DFA if it would have more states than this.
+	#[arg(long)]
+	max_states: Option<usize>,
+
+	/// Stop converting to a regex, or writing out a bounded repetition as copies, if the regex grows larger than this many operators and letters.
+	#[arg(long)]
+	max_regex_size: Option<usize>,
+
+	/// Stop a conversion to a DFA, NFA or regex, or simplifying or printing a regex, taking longer than this many seconds.
+	#[arg(long)]
+	timeout: Option<f64>,
 }
 
 struct Automata {
 	dfa: Option<DFA>,
 	nfa: Option<NFA>,
-	regex: Option<Regex>
+	regex: Option<Regex>,
+	regex_options: RegexOptions,
+	nfa_construction: NfaConstruction,
+	dfa_construction: DfaConstruction,
+	limits: Limits,
+	canonical: bool,
+	partial_dfa: bool
 }
 
 impl Automata {
 
-	fn new(cli:&Cli) -> Result<Automata,String> {
-		let input_type:InputType = match get_input_type(cli) {
+	fn new(source:&InputArgs, conversion:&ConversionArgs) -> Result<Automata,Error> {
+		let input_type:InputType = match get_input_type(source) {
 			Err(e) => return Err(e),
 			Ok(it) => it
 		};
 
-		let lines:Vec<String>;
-		if input_type == InputType::Regex {
-			lines=Vec::new();
+		let lines:Vec<String> = if input_type == InputType::Regex {
+			Vec::new()
 		} else {
-			lines = match read_input_file(cli.input.as_deref().unwrap()) {
+			match read_input_file(source.input.as_deref().unwrap()) {
 				Err(e) => return Err(e),
 				Ok(l) => l
-			};
-		}
-		return match input_type {
+			}
+		};
+		let autos = match input_type {
 			InputType::Dfa => Automata::new_dfa(lines),
 			InputType::Nfa => Automata::new_nfa(lines),
-			InputType::Regex => Automata::new_regex(cli.regex.as_deref().unwrap())
+			InputType::Regex => Automata::new_regex(source.regex.as_deref().unwrap(),source.alphabet.as_deref(),source.lenient)
+		};
+		let timeout = match conversion.timeout.map(Duration::try_from_secs_f64) {
+			None => None,
+			Some(Ok(t)) => Some(t),
+			Some(Err(_)) => return Err(Error::usage("The timeout must be a non-negative number of seconds".to_string()))
+		};
+		let mut autos = match autos {
+			Err(e) => return Err(Error::parse(e)),
+			Ok(a) => a
 		};
+		autos.regex_options = RegexOptions{simplify:!conversion.raw_regex,algorithm:conversion.regex_algorithm,order:conversion.elimination_order};
+		autos.nfa_construction = conversion.nfa_construction;
+		autos.dfa_construction = conversion.dfa_construction;
+		autos.limits = Limits{max_states:conversion.max_states,max_regex_size:conversion.max_regex_size,timeout};
+		if !source.transform.is_empty() {
+			if let Err(e) = autos.transform(&source.transform) {
+				return Err(e);
+			}
+		}
+		return Ok(autos);
 	}
 
 	fn new_dfa(lines:Vec<String>) -> Result<Automata,String> {
@@ -80,7 +233,7 @@ impl Automata {
 		};
 		let nfa = None;
 		let regex = None;
-		return Ok(Automata{dfa,nfa,regex});
+		return Ok(Automata{dfa,nfa,regex,regex_options:RegexOptions::default(),nfa_construction:NfaConstruction::Thompson,dfa_construction:DfaConstruction::Subset,limits:Limits::default(),canonical:false,partial_dfa:false});
 	}
 
 	fn new_nfa(lines:Vec<String>) -> Result<Automata,String> {
@@ -90,80 +243,280 @@ impl Automata {
 			Ok(nfa_in) => Some(nfa_in)
 		};
 		let regex = None;
-		return Ok(Automata{dfa,nfa,regex});
+		return Ok(Automata{dfa,nfa,regex,regex_options:RegexOptions::default(),nfa_construction:NfaConstruction::Thompson,dfa_construction:DfaConstruction::Subset,limits:Limits::default(),canonical:false,partial_dfa:false});
 	}
 
-	fn new_regex(regex_str:&str) -> Result<Automata,String> {
+	fn new_regex(regex_str:&str,alphabet:Option<&str>,lenient:bool) -> Result<Automata,String> {
 		let dfa = None;
 		let nfa = None;
-		let regex:Option<Regex> = match Regex::try_from(regex_str.to_string()) {
+		let regex:Option<Regex> = match Regex::parse(regex_str,alphabet,lenient) {
 			Err(e) => return Err(e),
 			Ok(reg) => Some(reg)
 		};
-		return Ok(Automata{dfa,nfa,regex});
+		return Ok(Automata{dfa,nfa,regex,regex_options:RegexOptions::default(),nfa_construction:NfaConstruction::Thompson,dfa_construction:DfaConstruction::Subset,limits:Limits::default(),canonical:false,partial_dfa:false});
+	}
+
+
+	fn ensure_nfa(&mut self) -> Result<(),Error> {
+		if self.nfa.is_none() {
+			self.nfa = match self.dfa.is_some() {
+				true => Some(NFA::from(self.dfa.as_ref().unwrap())),
+				false => match NFA::from_regex(self.regex.as_ref().unwrap(),self.nfa_construction,&self.limits) {
+					Ok(nfa) => Some(nfa),
+					Err(e) => return Err(e)
+				}
+			};
+		}
+		return Ok(());
 	}
 
+	/// Replaces the automata by an NFA for the transformed language, or a DFA while the input is a DFA and the transformations keep it deterministic.
+	fn transform(&mut self, transforms:&Vec<Transform>) -> Result<(),Error> {
+		for t in transforms {
+			if self.nfa.is_none() {
+				if let Some(dfa) = self.dfa.as_ref().and_then(|dfa| t.apply_dfa(dfa)) {
+					self.dfa = Some(dfa);
+					continue;
+				}
+			}
+			if let Err(e) = self.ensure_nfa() {
+				return Err(e);
+			}
+			self.nfa = match t.apply(self.nfa.as_ref().unwrap(),&self.limits) {
+				Ok(n) => Some(n),
+				Err(e) => return Err(e)
+			};
+			self.dfa = None;
+			self.regex = None;
+		}
+		return Ok(());
+	}
 
-	fn ensure_dfa(&mut self) {
-		if !self.dfa.is_some() {
-			if !self.nfa.is_some() {
-				self.nfa = Some(NFA::from(self.regex.as_ref().unwrap()));
+	fn ensure_dfa(&mut self) -> Result<(),Error> {
+		if self.dfa.is_none() {
+			if self.nfa.is_none() && self.dfa_construction == DfaConstruction::Derivative {
+				self.dfa = match DFA::from_derivatives(self.regex.as_ref().unwrap(),&self.limits) {
+					Ok(dfa) => Some(dfa),
+					Err(e) => return Err(Error::from(e))
+				};
+				return Ok(());
 			}
-			self.dfa = Some(DFA::from(self.nfa.as_ref().unwrap()));
+			if let Err(e) = self.ensure_nfa() {
+				return Err(e);
+			}
+			self.dfa = match DFA::try_from_nfa(self.nfa.as_ref().unwrap(),&self.limits) {
+				Ok(dfa) => Some(dfa),
+				Err(e) => return Err(Error::from(e))
+			};
 		}
+		return Ok(());
 	}
+
 	fn run(&mut self, word:&str) -> Rslt {
 		if !(self.regex.is_some()||self.nfa.is_some()||self.dfa.is_some()) {
-			return Rslt::Err("Automata list is unitialised".to_string());
+			return Rslt::Err(Error::usage("Automata list is unitialised".to_string()));
+		}
+		if !(self.nfa.is_some()||self.dfa.is_some()) {
+			// matching a regex directly is cheaper than building the automata for a single word
+			return match self.regex.as_ref().unwrap().matches(word) {
+				true => Rslt::Acc,
+				false => Rslt::Rej
+			};
+		}
+		if let Err(e) = self.ensure_dfa() {
+			return Rslt::Err(e);
 		}
-		self.ensure_dfa();
 		return self.dfa.as_ref().unwrap().run(word);
 	}
 
-	fn output_dfa(&mut self,address:&str) -> Result<(),String>{
+	/// Traces the run on the word of the DFA if there is one, or else of the NFA, which is cheaper to build from a regex.
+	fn trace(&mut self, word:&str) -> Result<Trace,Error> {
 		if !(self.regex.is_some()||self.nfa.is_some()||self.dfa.is_some()) {
-			return Err("Automata list is unitialised".to_string());
+			return Err(Error::usage("Automata list is unitialised".to_string()));
+		}
+		if let Some(dfa) = &self.dfa {
+			return Ok(dfa.trace(word));
+		}
+		if let Err(e) = self.ensure_nfa() {
+			return Err(e);
 		}
-		self.ensure_dfa();
-		return match print_to_file(self.dfa.as_ref().unwrap().to_string(),address) {
+		return Ok(self.nfa.as_ref().unwrap().trace(word));
+	}
+
+	/// Tests the word, reporting each step of the run first if asked to.
+	fn match_word(&mut self, word:&str, trace:bool, report:&mut Report) -> Rslt {
+		if !trace {
+			return self.run(word);
+		}
+		return match self.trace(word) {
+			Err(e) => Rslt::Err(e),
+			Ok(trace) => {
+				report.result("trace",&trace,trace.to_string());
+				match trace.accepted() {
+					true => Rslt::Acc,
+					false => Rslt::Rej
+				}
+			}
+		};
+	}
+
+	/// Reports whether a regex input is deterministic when the Glushkov construction is chosen, as its automaton shows this for free.
+	fn report_determinism(&self, report:&mut Report) -> Result<(),Error> {
+		if self.nfa_construction != NfaConstruction::Glushkov {
+			return Ok(());
+		}
+		if let Some(regex) = &self.regex {
+			match regex.is_deterministic(&self.limits) {
+				Ok(true) => report.note("deterministic",true,"The regex is deterministic (one-unambiguous).".to_string()),
+				Ok(false) => report.note("deterministic",false,"The regex is not deterministic (one-unambiguous).".to_string()),
+				Err(e) => return Err(e)
+			}
+		}
+		return Ok(());
+	}
+
+	/// The DFA as written to a .dfa file, minimized and in canonical form or partial if those options are set.
+	fn dfa_text(&mut self) -> Result<String,Error> {
+		if !(self.regex.is_some()||self.nfa.is_some()||self.dfa.is_some()) {
+			return Err(Error::usage("Automata list is unitialised".to_string()));
+		}
+		if let Err(e) = self.ensure_dfa() {
+			return Err(e);
+		}
+		let dfa = match self.canonical {
+			true => self.dfa.as_ref().unwrap().minimize(),
+			false => self.dfa.clone().unwrap()
+		};
+		let dfa = match self.partial_dfa {
+			true => dfa.to_partial(),
+			false => dfa.to_complete()
+		};
+		return Ok(dfa.to_string());
+	}
+
+	fn output_dfa(&mut self, address:&str, report:&mut Report) -> Result<(),Error>{
+		let text = match self.dfa_text() {
+			Ok(text) => text,
+			Err(e) => return Err(e)
+		};
+		return match print_to_file(text,address) {
 			Ok(()) => {
-				println!("DFA written to {}",address);
+				report.note("dfa_output",address,format!("DFA written to {}",address));
 				Ok(())
 			},
 			Err(e) => Err(e)
 		}
 	}
 
-	fn output_nfa(&mut self,address:&str) -> Result<(),String> {
-		if !(self.regex.is_some()||self.nfa.is_some()||self.dfa.is_some()) {
-			return Err("Automata list is unitialised".to_string());
+	/// Reports whether the DFA is the DFA in the file up to the numbering of the states, and which states match if so.
+	fn compare_dfa(&mut self, address:&str, report:&mut Report) -> Result<bool,Error> {
+		let other = match read_input_file(address) {
+			Err(e) => return Err(e),
+			Ok(lines) => match DFA::try_from(lines) {
+				Err(e) => return Err(Error::parse(e)),
+				Ok(dfa) => dfa
+			}
+		};
+		if let Err(e) = self.ensure_dfa() {
+			return Err(e);
 		}
-		if !self.nfa.is_some() {
-			match self.dfa.is_some() {
-				true => self.nfa = Some(NFA::from(self.dfa.as_ref().unwrap())),
-				false => self.nfa = Some(NFA::from(self.regex.as_ref().unwrap()))
+		return match self.dfa.as_ref().unwrap().is_isomorphic(&other) {
+			None => {
+				report.result("isomorphic",false,format!("The DFA is not isomorphic to {}",address));
+				Ok(false)
+			},
+			Some(mapping) => {
+				let pairs:Vec<String> = mapping.iter().enumerate().map(|(s,t)| format!("{}->{}",s+1,t+1)).collect();
+				report.result("isomorphic",true,format!("The DFA is isomorphic to {}, matching states {}",address,pairs.join(", ")));
+				report.data("matching_states",mapping.iter().map(|t| t+1).collect::<Vec<usize>>());
+				Ok(true)
 			}
+		};
+	}
+
+	/// The NFA as written to a .nfa file.
+	fn nfa_text(&mut self) -> Result<String,Error> {
+		if !(self.regex.is_some()||self.nfa.is_some()||self.dfa.is_some()) {
+			return Err(Error::usage("Automata list is unitialised".to_string()));
+		}
+		if let Err(e) = self.ensure_nfa() {
+			return Err(e);
 		}
-		return match print_to_file(self.nfa.as_ref().unwrap().to_string(),address) {
+		return Ok(self.nfa.as_ref().unwrap().to_string());
+	}
+
+	fn output_nfa(&mut self, address:&str, report:&mut Report) -> Result<(),Error> {
+		let text = match self.nfa_text() {
+			Ok(text) => text,
+			Err(e) => return Err(e)
+		};
+		return match print_to_file(text,address) {
 			Ok(()) => {
-				println!("NFA written to {}",address);
+				report.note("nfa_output",address,format!("NFA written to {}",address));
 				Ok(())
 			},
 			Err(e) => Err(e)
 		}
 	}
 
-	fn output_regex(&mut self) -> Result<(),String>{
+	fn ensure_regex(&mut self) -> Result<(),Error> {
 		if !(self.regex.is_some()||self.nfa.is_some()||self.dfa.is_some()) {
-			return Err("Automata list is unitialised".to_string());
+			return Err(Error::usage("Automata list is unitialised".to_string()));
 		}
-		if !self.regex.is_some() {
-			if !self.nfa.is_some() {
+		if self.regex.is_none() {
+			if self.nfa.is_none() {
 				self.nfa = Some(NFA::from(self.dfa.as_ref().unwrap()));
 			}
-			self.regex = Some(Regex::from(self.nfa.as_ref().unwrap()));
+			self.regex = match int_nfa_reg::convert(self.nfa.as_ref().unwrap(),&self.regex_options,&self.limits) {
+				Ok(regex) => Some(regex),
+				Err(e) => return Err(Error::from(e))
+			};
+		}
+		return Ok(());
+	}
+
+	fn output_regex(&mut self, report:&mut Report) -> Result<(),Error>{
+		if let Err(e) = self.ensure_regex() {
+			return Err(e);
+		}
+		let regex = match self.regex.as_ref().unwrap().to_string_within(&self.limits) {
+			Ok(r) => r,
+			Err(e) => return Err(Error::from(e))
+		};
+		report.result("regex",regex.as_str(),format!("Regex is: {}",regex));
+		return Ok(());
+	}
+
+	/// Replaces the automata by the minimal DFA of the language.
+	fn minimize(&mut self) -> Result<(),Error> {
+		if let Err(e) = self.ensure_dfa() {
+			return Err(e);
 		}
-		println!("Regex is: {}",self.regex.as_ref().unwrap().to_string());
+		self.dfa = Some(self.dfa.as_ref().unwrap().minimize());
+		self.nfa = None;
+		self.regex = None;
+		return Ok(());
+	}
+
+	/// Replaces the automata by a DFA for the complement of the language, over the same alphabet.
+	fn complement(&mut self) -> Result<(),Error> {
+		if let Err(e) = self.ensure_dfa() {
+			return Err(e);
+		}
+		self.dfa = Some(self.dfa.as_ref().unwrap().complement());
+		self.nfa = None;
+		self.regex = None;
+		return Ok(());
+	}
+
+	/// Replaces the automata by an NFA for the reverse of the language.
+	fn reverse(&mut self) -> Result<(),Error> {
+		if let Err(e) = self.ensure_nfa() {
+			return Err(e);
+		}
+		self.nfa = Some(self.nfa.as_ref().unwrap().reverse());
+		self.dfa = None;
+		self.regex = None;
 		return Ok(());
 	}
 
@@ -181,112 +534,248 @@ pub enum Rslt {
     Rej,//the word is rejected
     Nop,//no word is provided "no operation performed"
     Notodo, //nothing to do, no word or output file provided
-    Err(String) // some error occurred, due to invalid input
+    Done, //a subcommand finished, having reported its own output
+    Differ, //a comparison found the languages or DFAs different
+    Err(Error) // some error occurred, due to invalid input
 }
 
-fn main() {
+impl Rslt {
+	/// The exit code of the program: 0 when the word is accepted or the work is done, 1 when the word is rejected or the comparison fails, and 2 or more for the kinds of error.
+	fn exit_code(&self) -> u8 {
+		return match self {
+			Rslt::Acc | Rslt::Nop | Rslt::Done => 0,
+			Rslt::Rej | Rslt::Differ => 1,
+			Rslt::Notodo => 2,
+			Rslt::Err(e) => match e.kind {
+				ErrorKind::Usage => 2,
+				ErrorKind::Parse | ErrorKind::Unsupported => 3,
+				ErrorKind::Io => 4,
+				ErrorKind::Limit => 5
+			}
+		};
+	}
 
-    println!("Use the --help option (i.e. cargo run -- --help) to learn about possible options.");
+	/// The name of the outcome in the JSON output.
+	fn name(&self) -> &'static str {
+		return match self {
+			Rslt::Acc => "accept",
+			Rslt::Rej => "reject",
+			Rslt::Nop | Rslt::Done => "done",
+			Rslt::Notodo => "nothing to do",
+			Rslt::Differ => "different",
+			Rslt::Err(_) => "error"
+		};
+	}
+}
+
+fn main() -> ExitCode {
+
+    let matches = match Cli::command().try_get_matches() {
+		Ok(matches) => matches,
+		Err(e) => return argument_error(e)
+    };
+    let cli = match Cli::from_arg_matches(&matches) {
+		Ok(cli) => cli,
+		Err(e) => return argument_error(e)
+    };
+    let mut report = Report::new(cli.format,cli.quiet);
+    let ignored = options_before_subcommand(&matches);
+    let result = match &cli.command {
+		Some(_) if !ignored.is_empty() => Rslt::Err(Error::usage(format!("{} cannot be given before a subcommand, give the options of a subcommand after its name",ignored.join(", ")))),
+		Some(command) => command.run(&mut report),
+		None => run_automata(&cli,&mut report)
+	};
+	return finish(report,result);
+}
 
-    let cli = Cli::parse();
-    let result=run_automata(&cli);
-    println!("{}", match result {
-		Rslt::Err(e) => format!("Program failed! The following error was produced: \n{}",e),
-		Rslt::Acc => "ACCEPT".to_string(),
-		Rslt::Rej => "REJECT".to_string(),
-		Rslt::Nop => "No word provided, program finished without computation, only conversion.".to_string(),
-		Rslt::Notodo => "No word or output file provided, nothing to do.".to_string(),
-    });
-    return;
+/// Prints the outcome of the program and gives its exit code.
+fn finish(report:Report, result:Rslt) -> ExitCode {
+	let code = ExitCode::from(result.exit_code());
+	if report.format == Format::Json {
+		println!("{}",report.into_json(outcome(&result)));
+		return code;
+	}
+	match result {
+		Rslt::Err(e) => eprintln!("Program failed! The following error was produced: \n{}",e),
+		Rslt::Notodo => eprintln!("No word or output file provided, nothing to do. Use the --help option (i.e. cargo run -- --help) to learn about possible options."),
+		_ if report.quiet => (),
+		Rslt::Acc => println!("ACCEPT"),
+		Rslt::Rej => println!("REJECT"),
+		Rslt::Nop => println!("No word provided, program finished without computation, only conversion."),
+		Rslt::Done | Rslt::Differ => ()
+	}
+	return code;
 }
 
-fn run_automata(cli:&Cli) -> Rslt {
+/// Reports a mistake in the arguments. With --format json it is a usage error like any other, otherwise clap prints it with the usage text. --help and --version are always left to clap.
+fn argument_error(e:clap::Error) -> ExitCode {
+	let args:Vec<String> = std::env::args().collect();
+	return match json_argument_error(&args,&e) {
+		None => e.exit(),
+		Some(result) => finish(Report::new(Format::Json,false),result)
+	};
+}
 
-	if !(cli.word.as_deref().is_some() ||cli.dfa_output.as_deref().is_some()||cli.nfa_output.as_deref().is_some()||cli.regex_output) {
+/// The usage error to report as JSON for a mistake in the arguments, or None if clap should print it.
+fn json_argument_error(args:&[String], e:&clap::Error) -> Option<Rslt> {
+	let json = args.iter().any(|a| a == "--format=json") || args.windows(2).any(|w| w[0] == "--format" && w[1] == "json");
+	if !json || matches!(e.kind(),clap::error::ErrorKind::DisplayHelp | clap::error::ErrorKind::DisplayVersion | clap::error::ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand) {
+		return None;
+	}
+	// the message without the tips and usage text clap adds after a blank line
+	let text = e.to_string();
+	let message = text.split("\n\n").next().unwrap().trim().trim_start_matches("error: ").to_string();
+	return Some(Rslt::Err(Error::usage(message)));
+}
+
+/// The fields of the JSON output giving the outcome, and the error if there is one.
+fn outcome(result:&Rslt) -> Vec<(String,Json)> {
+	let mut outcome = vec![("outcome".to_string(),Json::from(result.name()))];
+	if let Rslt::Err(e) = result {
+		outcome.push(("error".to_string(),Json::Object(vec![
+			("kind".to_string(),Json::from(e.kind.name())),
+			("message".to_string(),Json::from(e.message.as_str()))
+		])));
+	}
+	return outcome;
+}
+
+/// The options given on the command line that only apply without a subcommand, so would be ignored with one. --quiet and --format apply to both.
+fn options_before_subcommand(matches:&clap::ArgMatches) -> Vec<String> {
+	return Cli::command().get_arguments()
+		.filter(|arg| !arg.is_global_set())
+		.filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+		.map(|arg| format!("--{}",arg.get_long().unwrap()))
+		.collect();
+}
+
+fn run_automata(cli:&Cli, report:&mut Report) -> Rslt {
+
+	if !(cli.word.as_deref().is_some() ||cli.dfa_output.as_deref().is_some()||cli.nfa_output.as_deref().is_some()||cli.regex_output||cli.isomorphic_to.is_some()) {
 		return Rslt::Notodo;
 	}
 
-	let mut autos:Automata = match Automata::new(cli) {
+	let mut autos:Automata = match Automata::new(&cli.source,&cli.conversion) {
 		Err(e) => return Rslt::Err(e),
 		Ok(a) => a
 	};
+	autos.canonical = cli.canonical;
+	autos.partial_dfa = cli.partial_dfa;
+
+	if let Err(e) = autos.report_determinism(report) {
+		return Rslt::Err(e);
+	}
 
 	if cli.regex_output {
-		match autos.output_regex() {
+		match autos.output_regex(report) {
 			Ok(()) => (),
 			Err(e) => return Rslt::Err(e)
 		}
 	}
 
 	if let Some(address) = cli.nfa_output.as_deref() {
-		match autos.output_nfa(address) {
+		match autos.output_nfa(address,report) {
 			Ok(()) => (),
 			Err(e) => return Rslt::Err(e)
 		}
 	}
 
 	if let Some(address) = cli.dfa_output.as_deref() {
-		match autos.output_dfa(address) {
+		match autos.output_dfa(address,report) {
 			Ok(()) => (),
 			Err(e) => return Rslt::Err(e)
 		}
 	}
 
+	let mut isomorphic = true;
+	if let Some(address) = cli.isomorphic_to.as_deref() {
+		match autos.compare_dfa(address,report) {
+			Ok(same) => isomorphic = same,
+			Err(e) => return Rslt::Err(e)
+		}
+	}
+
 	if let Some(word) = cli.word.as_deref() {
-		return autos.run(word);
+		return autos.match_word(word,cli.trace,report);
 	}
-	return Rslt::Nop;
+	return match isomorphic {
+		true => Rslt::Nop,
+		false => Rslt::Differ
+	};
 }
 
 
-fn get_input_type(cli:&Cli) -> Result<InputType,String> {
-	let is_regex = cli.regex.as_deref().is_some();
-	return match &cli.input.as_deref() {
+fn get_input_type(source:&InputArgs) -> Result<InputType,Error> {
+	let is_regex = source.regex.as_deref().is_some();
+	return match &source.input.as_deref() {
 		None => match is_regex {
 			true => Ok(InputType::Regex),
-			false => Err("No automata or regex provided.".to_string())
+			false => Err(Error::usage("No automata or regex provided.".to_string()))
 		},
+		Some(_) if source.alphabet.is_some() => Err(Error::usage("An alphabet can only be declared for a regex".to_string())),
 		Some(address) => match is_regex {
-			true => Err("Cannot input both regex and other automata".to_string()),
-			false => match address.split('.').last().unwrap().to_uppercase().as_str() {
-				"DFA" => Ok(InputType::Dfa),
-				"NFA" => Ok(InputType::Nfa),
-				_ => {
-					return Err("File type is unsupported.".to_string());
-				}
-			}
+			true => Err(Error::usage("Cannot input both regex and other automata".to_string())),
+			false => get_file_type(address)
 		}
 	}
 }
 
-fn read_input_file(address:&str) -> Result<Vec<String>,String> {
+fn get_file_type(address:&str) -> Result<InputType,Error> {
+	return match address.split('.').next_back().unwrap().to_uppercase().as_str() {
+		"DFA" => Ok(InputType::Dfa),
+		"NFA" => Ok(InputType::Nfa),
+		_ => Err(Error::usage(format!("File type is unsupported ({}).",address)))
+	};
+}
+
+/// Reads a .dfa or .nfa file as an NFA.
+fn read_automaton(address:&str) -> Result<NFA,Error> {
+	let input_type = match get_file_type(address) {
+		Err(e) => return Err(e),
+		Ok(t) => t
+	};
+	let lines = match read_input_file(address) {
+		Err(e) => return Err(e),
+		Ok(l) => l
+	};
+	let nfa = match input_type {
+		InputType::Dfa => DFA::try_from(lines).map(|dfa| NFA::from(&dfa)),
+		_ => NFA::try_from(lines)
+	};
+	return nfa.map_err(|e| Error::parse(format!("{} ({})",e,address)));
+}
+
+fn read_input_file(address:&str) -> Result<Vec<String>,Error> {
     let mut contents= String::new();
     match File::open(address) {
-		Ok(mut f) => _ = f.read_to_string(&mut contents),
-		Err(_) => {
-			return Err("Cannot read file.".to_string());
+		Ok(mut f) => if let Err(e) = f.read_to_string(&mut contents) {
+			return Err(Error::io(format!("Cannot read file {}: {}",address,e)));
+		},
+		Err(e) => {
+			return Err(Error::io(format!("Cannot read file {}: {}",address,e)));
 		}
     }
     let lines = contents.lines().map(|s| s.to_string()).collect();
     return Ok(lines);
 }
 
-fn print_to_file(val:String,address:&str) -> Result<(),String> {
-    if let Ok(mut file_ptr) = File::create(address){
-		match write!(file_ptr, "{}", val) {
-			Ok(_) => (),
-			Err(e) => return Err(e.to_string())
-		}
-    }
-    return Ok(());
+fn print_to_file(val:String,address:&str) -> Result<(),Error> {
+    let mut file_ptr = match File::create(address) {
+		Ok(f) => f,
+		Err(e) => return Err(Error::io(format!("Cannot write file {}: {}",address,e)))
+    };
+	return match write!(file_ptr, "{}", val) {
+		Ok(_) => Ok(()),
+		Err(e) => Err(Error::io(format!("Cannot write file {}: {}",address,e)))
+	};
 }
 
 
 fn get_alphabet(alphabet:&str) -> Result<String,String> {
-	let invalid_letters = vec![':',','];
+	let invalid_letters = [':',','];
 	let mut result:Vec<char> = Vec::new();
 	for c in alphabet.chars() {
-		if invalid_letters.contains(&c) || Regex::VALID_SYMBOLS.contains(&c){
+		if invalid_letters.contains(&c) {
 			return Err(format!("The alphabet cannot contain {}",c));
 		}
 		if !result.contains(&c) {
@@ -303,14 +792,14 @@ fn get_alphabet_hm(alphabet:&str) -> HashMap<char,Index0> {
 	let mut alphabet_hashmap = HashMap::<char,Index0>::new();
 	let mut i = Index0(0); //not to be read here, only passed to dfa so ignoring jump is fine
 	for c in alphabet {
-		if !alphabet_hashmap.contains_key(&c) {
-			alphabet_hashmap.insert(c,i);
+		if let std::collections::hash_map::Entry::Vacant(e) = alphabet_hashmap.entry(c) {
+			e.insert(i);
 			i = Index0(i.0 + 1);
 		}
 	}
 	return alphabet_hashmap;
 }
-#[derive(Clone,Copy,Debug)]
+#[derive(Clone,Copy,Debug,Hash,Eq,PartialEq,PartialOrd,Ord)]
 struct Index0(usize); // alphabet indexing with first letter at 0
 
 #[derive(Clone,Copy,Debug)]
@@ -337,9 +826,9 @@ impl Ordered {
 	let Self(v1) = self;
 	let Self(v2) = v2;
 
-	if v1.len()==0 {
+	if v1.is_empty() {
 		return Self(v2.to_vec());
-	} else if v2.len()==0 {
+	} else if v2.is_empty() {
 		return Self(v1.to_vec());
 	}
 
@@ -358,24 +847,22 @@ impl Ordered {
 			consider_v1 = false;
 		} else if j==v2.len() {
 			consider_v1 = true;
-		} else if v1[i]<=v2[j] {
-			consider_v1 = true;
 		} else {
-			consider_v1 = false;
+			consider_v1 = v1[i]<=v2[j];
 		}
 		if consider_v1 {
 			if result.last().unwrap() == &v1[i] {
-				i=i+1
+				i += 1
 			} else {
 				result.push(v1[i]);
-				i=i+1;
+				i += 1;
 			}
 		} else {
 			if result.last().unwrap() == &v2[j] {
-				j=j+1
+				j += 1
 			} else {
 				result.push(v2[j]);
-				j=j+1;
+				j += 1;
 			}
 		}
 	}
@@ -383,3 +870,45 @@ impl Ordered {
 }
 
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn exit_codes() {
+		let cases = [
+			(Rslt::Acc,0),
+			(Rslt::Nop,0),
+			(Rslt::Done,0),
+			(Rslt::Rej,1),
+			(Rslt::Differ,1),
+			(Rslt::Notodo,2),
+			(Rslt::Err(Error::usage(String::new())),2),
+			(Rslt::Err(Error::parse(String::new())),3),
+			(Rslt::Err(Error::unsupported(String::new())),3),
+			(Rslt::Err(Error::io(String::new())),4),
+			(Rslt::Err(Error::new(ErrorKind::Limit,String::new())),5)
+		];
+		for (result,code) in cases {
+			assert_eq!(result.exit_code(),code,"{}",result.name());
+		}
+	}
+
+	fn argument_error(args:&[&str]) -> Option<Rslt> {
+		let args:Vec<String> = args.iter().map(|a| a.to_string()).collect();
+		let e = Cli::command().try_get_matches_from(&args).err().unwrap();
+		return json_argument_error(&args,&e);
+	}
+
+	#[test]
+	fn argument_errors_as_json() {
+		for args in [["prog","--format","json","--bogus"],["prog","--bogus","x","--format=json"]] {
+			let result = argument_error(&args).unwrap();
+			assert_eq!(result.exit_code(),2);
+			assert_eq!(Json::Object(outcome(&result)).to_string(),r#"{"outcome":"error","error":{"kind":"usage","message":"unexpected argument '--bogus' found"}}"#);
+		}
+		assert!(argument_error(&["prog","--bogus"]).is_none());
+		assert!(argument_error(&["prog","--format","json","--help"]).is_none());
+	}
+}
@@ -12,6 +12,9 @@ use crate::dfa::DFAState;
 
 use crate::regex::Regex;
 
+use crate::error::Error;
+use crate::limits::Limits;
+
 use crate::Index1;
 use crate::Index0;
 
@@ -67,7 +70,7 @@ impl NFAState {
 		let num_parts = comma_split.len();
 		let mut transitions:Vec<Vec<StateNum>> = vec![Vec::new();alphabet.len()+1]; // +1 because of the empty letter, the jump, is not in the alphabet but does have transitions
 
-		for transition in (&comma_split[0..num_parts-1]).into_iter() {
+		for transition in comma_split[0..num_parts-1].iter() {
 			let (transition_num,value) = match NFAState::get_transition(transition,alphabet,max_state) {
 				Ok(result) => result,
 				Err(e) => return Err(e)
@@ -83,16 +86,16 @@ impl NFAState {
 		};
 
 		let mut new_transitions:Vec<Ordered> = Vec::new();
-		for i in 0..transitions.len() {
-			transitions[i].sort();
-			new_transitions.push(Ordered(transitions[i].clone()));
+		for transition in transitions.iter_mut() {
+			transition.sort();
+			new_transitions.push(Ordered(transition.clone()));
 		}
 		let transitions = new_transitions;
 		return Ok(NFAState::new(transitions,accepting));
 	}
 	fn to_string(&self, alphabet:&str) -> String {
 		let mut output:String = String::new();
-		for i in 0..alphabet.len() {
+		for i in 0..alphabet.chars().count() {
 			let index:Index0 = Index0(i);//for alphabet
 			let trans_num:Index1 = index.into();//for transitions list
 			let letter = alphabet.chars().nth(index.0).unwrap();
@@ -118,13 +121,27 @@ impl From<&DFAState> for NFAState {
 		let mut transitions:Vec<Ordered> = Vec::new();
 		transitions.push(Ordered(Vec::new()));
 		for next in &dfastate.transitions {
-			transitions.push(Ordered(vec![*next]));
+			transitions.push(match next {
+				Some(next) => Ordered(vec![*next]),
+				None => Ordered(Vec::new())
+			});
 		}
 		return NFAState::new(transitions,dfastate.accepting);
 
 	}
 }
 
+/// How an NFA is built from a regex.
+#[derive(clap::ValueEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum NfaConstruction {
+	/// Thompson's construction, joining NFAs for each part of the regex with jumps.
+	Thompson,
+	/// Antimirov's partial derivatives, giving an NFA without jumps.
+	Antimirov,
+	/// Glushkov's position automaton, giving an NFA without jumps with a state for each letter of the regex.
+	Glushkov
+}
+
 #[derive(Clone,Debug)]
 pub struct NFA {
 	pub states:Vec<NFAState>,
@@ -137,16 +154,75 @@ impl NFA {
 		NFA{states, starting, alphabet}
 	}
 	pub fn get_never_accept(alphabet:String) -> NFA {
-		let states = vec![NFAState::new(vec![],false)];
+		let states = vec![NFAState::new(vec![Ordered(Vec::new());alphabet.chars().count()+1],false)];
 		let starting = 0;
 		return NFA::new(states,starting,alphabet);
 	}
 	
 	pub fn get_accept_empty(alphabet:String) -> Result<NFA,String> {
-		let state = NFAState::new(vec![Ordered(Vec::new());alphabet.len()+1],true);
+		let state = NFAState::new(vec![Ordered(Vec::new());alphabet.chars().count()+1],true);
 		return Ok(NFA::new(vec![state],0,alphabet));
 	}
+	/// Whether any transition, including a jump or a loop, leads to the state.
+	fn has_incoming(&self, state:StateNum) -> bool {
+		return self.states.iter().any(|s| s.transitions.iter().any(|t| t.0.contains(&state)));
+	}
+
+	/// Makes every transition to `from` go to `to` instead.
+	fn redirect(&mut self, from:StateNum, to:StateNum) {
+		for s in &mut self.states {
+			for t in &mut s.transitions {
+				if t.0.contains(&from) {
+					let rest:Vec<StateNum> = t.0.iter().filter(|n| **n != from).cloned().collect();
+					*t = Ordered(rest).join(&Ordered(vec![to]));
+				}
+			}
+		}
+	}
+
+	/// Removes a state no transition leads to, renumbering the states after it.
+	fn remove_state(&mut self, removed:StateNum) {
+		self.states.remove(removed);
+		for s in &mut self.states {
+			for t in &mut s.transitions {
+				for n in &mut t.0 {
+					if *n > removed {
+						*n -= 1;
+					}
+				}
+			}
+		}
+		if self.starting > removed {
+			self.starting -= 1;
+		}
+	}
+
+	/// Adds the transitions of `from` to `to`, which accepts if either did.
+	fn copy_transitions(&mut self, from:StateNum, to:StateNum) {
+		let copied = self.states[from].clone();
+		let state = &mut self.states[to];
+		for i in 0..state.transitions.len() {
+			state.transitions[i] = state.transitions[i].join(&copied.transitions[i]);
+		}
+		state.accepting = state.accepting || copied.accepting;
+	}
+
+	fn jump_back_to_start(&mut self) {
+		let start = self.starting;
+		for s in &mut self.states {
+			if s.accepting {
+				s.transitions[0] = s.transitions[0].join(&Ordered(vec![start]));
+			}
+		}
+	}
+
 	pub fn make_kstar(&mut self) {
+		if !self.has_incoming(self.starting) {
+			// nothing can return to the start, so it can accept and be jumped back to directly
+			self.jump_back_to_start();
+			self.states[self.starting].accepting = true;
+			return;
+		}
 		let len = self.states.len();
 		for s in &mut self.states {
 			if s.accepting {
@@ -154,16 +230,32 @@ impl NFA {
 				s.accepting = false
 			}
 		}
-		let mut new_transitions:Vec<Ordered> = vec![Ordered(Vec::new());self.alphabet.len()+1];
+		let mut new_transitions:Vec<Ordered> = vec![Ordered(Vec::new());self.alphabet.chars().count()+1];
 		new_transitions[0]= Ordered(vec![self.starting]);
 		self.states.push(NFAState::new(new_transitions,true));
 		self.starting = len;
 	}
+
+	pub fn make_kplus(&mut self) {
+		self.jump_back_to_start();
+	}
+
+	pub fn make_optional(&mut self) {
+		if !self.has_incoming(self.starting) {
+			self.states[self.starting].accepting = true;
+			return;
+		}
+		let mut new_transitions:Vec<Ordered> = vec![Ordered(Vec::new());self.alphabet.chars().count()+1];
+		new_transitions[0] = Ordered(vec![self.starting]);
+		self.starting = self.states.len();
+		self.states.push(NFAState::new(new_transitions,true));
+	}
+
 	pub fn get_accept_single(i:Index1,alphabet:String) -> Result<NFA,String> {
-		let mut transitions:Vec<Ordered> = vec![Ordered(Vec::new());alphabet.len()+1];
+		let mut transitions:Vec<Ordered> = vec![Ordered(Vec::new());alphabet.chars().count()+1];
 		transitions[i.0] = Ordered(vec![1]);
 		let start = NFAState::new(transitions,false);
-		let end = NFAState::new(vec![Ordered(Vec::new());alphabet.len()+1],true);
+		let end = NFAState::new(vec![Ordered(Vec::new());alphabet.chars().count()+1],true);
 		return Ok(NFA::new(vec![start,end],0,alphabet));
 	}
 
@@ -171,7 +263,7 @@ impl NFA {
 		for state in &mut r2.states {
 			for t in &mut state.transitions {
 				for i in &mut t.0 {
-					*i = *i + by;
+					*i += by;
 				}
 			}
 		}
@@ -184,22 +276,48 @@ impl NFA {
 		}
 		let num_states = r1.states.len();
 		let second_start = r2.starting + num_states;
-		
-		for state in &mut r1.states {
+		let accepting:Vec<StateNum> = (0..num_states).filter(|s| r1.states[*s].accepting).collect();
+		let dead_end = accepting.len() == 1 && r1.states[accepting[0]].transitions.iter().all(|t| t.0.is_empty());
+
+		Self::bump_states_append(r1,r2,num_states);
+		if dead_end {
+			// the only accepting state of r1 is left for good, so it can become the start of r2
+			let end = accepting[0];
+			r1.states[end] = r1.states[second_start].clone();
+			r1.redirect(second_start,end);
+			r1.remove_state(second_start);
+			return Ok((*r1).clone());
+		}
+		for state in &mut r1.states[..num_states] {
 			if state.accepting {
 				state.transitions[0] = state.transitions[0].join(&Ordered(vec![second_start]));
 				state.accepting = false;
 			}
 		}
-		Self::bump_states_append(r1,r2,num_states);
-		return Ok((*r1).clone());		
+		return Ok((*r1).clone());
 	}
 
 	pub fn or(r1:&mut NFA, r2:&mut NFA) -> Result<NFA, String> {
 		let num_states = r1.states.len();
 		let second_start = r2.starting + num_states;
 		Self::bump_states_append(r1,r2,num_states);
-		let mut new_transitions = vec![Ordered(Vec::<StateNum>::new());r1.alphabet.len()+1];
+
+		// a start nothing returns to can take on the transitions of the other start
+		let first_free = !r1.has_incoming(r1.starting);
+		let second_free = !r1.has_incoming(second_start);
+		if first_free || second_free {
+			let (kept,other) = match first_free {
+				true => (r1.starting,second_start),
+				false => (second_start,r1.starting)
+			};
+			r1.copy_transitions(other,kept);
+			r1.starting = kept;
+			if first_free && second_free {
+				r1.remove_state(other);
+			}
+			return Ok((*r1).clone());
+		}
+		let mut new_transitions = vec![Ordered(Vec::<StateNum>::new());r1.alphabet.chars().count()+1];
 
 		let mut jumps = vec![r1.starting,second_start];
 		jumps.sort();
@@ -214,16 +332,35 @@ impl NFA {
 
 impl From<&DFA> for NFA {
 	fn from(dfa:&DFA) -> Self {
-		let states:Vec<NFAState> = (&dfa.states).into_iter().map(|s| NFAState::from(s)).collect();
+		let states:Vec<NFAState> = dfa.states.iter().map(NFAState::from).collect();
 		return NFA::new(states,dfa.starting,dfa.alphabet.clone());
 	}
 }
 
+impl NFA {
+	/// Builds an NFA for the regex with the chosen construction, stopping if it breaks the limits.
+	pub fn from_regex(reg:&Regex, construction:NfaConstruction, limits:&Limits) -> Result<NFA,Error> {
+		let budget = limits.start();
+		return match construction {
+			NfaConstruction::Thompson => match &reg.tree {
+				None => Ok(NFA::get_never_accept(reg.alphabet.clone())),
+				Some(tree) => tree.to_nfa(reg.alphabet.clone(),&budget).map_err(Error::from)
+			},
+			NfaConstruction::Antimirov => NFA::from_partial_derivatives(reg,&budget).map_err(Error::from),
+			NfaConstruction::Glushkov => NFA::from_glushkov(reg,&budget).map(|(nfa,_)| nfa)
+		};
+	}
+}
+
+/// Converts using Thompson's construction.
 impl From<&Regex> for NFA {
 	fn from(reg:&Regex) -> Self {
 		return match &reg.tree {
 			None => NFA::get_never_accept(reg.alphabet.clone()),
-			Some(tree) => tree.to_nfa(reg.alphabet.clone()).expect("This only fails if two generated alphabets are different, which indicates a programming error, not a user error")
+			Some(tree) => match tree.to_nfa(reg.alphabet.clone(),&Limits::default().start()) {
+				Ok(nfa) => nfa,
+				Err(e) => unreachable!("no limits were set, but {}",e)
+			}
 		};
 	}
 }
@@ -276,3 +413,136 @@ impl fmt::Display for NFA {
 		write!(f,"{}",output)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::regex::RegexTree;
+
+	fn parse(regex:&str) -> Regex {
+		return Regex::parse(regex,None,false).unwrap();
+	}
+
+	#[test]
+	fn writes_out_non_ascii_alphabets() {
+		let never = Regex::parse("\\0",Some("éb"),false).unwrap();
+		assert_eq!(NFA::from(&never).to_string(),"éb\n1\nfalse");
+		let letter = Regex::parse("b",Some("éb"),false).unwrap();
+		assert_eq!(NFA::from(&letter).to_string(),"éb\n1\nb:2,false\ntrue");
+	}
+
+	fn add_state(states:&mut Vec<NFAState>, letters:usize) -> StateNum {
+		states.push(NFAState::new(vec![Ordered(Vec::new());letters+1],false));
+		return states.len()-1;
+	}
+
+	fn add_transition(states:&mut [NFAState], from:StateNum, by:usize, to:StateNum) {
+		states[from].transitions[by] = states[from].transitions[by].join(&Ordered(vec![to]));
+	}
+
+	/// Thompson's construction as in the textbook, with a new start and end state for every part of the regex and no states merged. Returns the start and end states of the part.
+	fn unmerged(tree:&RegexTree, states:&mut Vec<NFAState>, letters:usize) -> (StateNum,StateNum) {
+		let start = add_state(states,letters);
+		let end = add_state(states,letters);
+		match tree {
+			RegexTree::Empty => add_transition(states,start,0,end),
+			RegexTree::Single(i) => add_transition(states,start,Index1::from(*i).0,end),
+			RegexTree::KleeneStar(r) | RegexTree::KleenePlus(r) | RegexTree::QMark(r) => {
+				let (s,e) = unmerged(r,states,letters);
+				add_transition(states,start,0,s);
+				add_transition(states,e,0,end);
+				if !matches!(tree,RegexTree::KleenePlus(_)) {
+					add_transition(states,start,0,end);
+				}
+				if !matches!(tree,RegexTree::QMark(_)) {
+					add_transition(states,e,0,s);
+				}
+			},
+			RegexTree::Concat((r1,r2)) => {
+				let (s1,e1) = unmerged(r1,states,letters);
+				let (s2,e2) = unmerged(r2,states,letters);
+				add_transition(states,start,0,s1);
+				add_transition(states,e1,0,s2);
+				add_transition(states,e2,0,end);
+			},
+			RegexTree::Or((r1,r2)) => {
+				for r in [r1,r2] {
+					let (s,e) = unmerged(r,states,letters);
+					add_transition(states,start,0,s);
+					add_transition(states,e,0,end);
+				}
+			},
+			_ => panic!("the tests only use the operators of Thompson's construction")
+		}
+		return (start,end);
+	}
+
+	/// All the words over the alphabet of at most the given length.
+	fn words(alphabet:&str, max_len:usize) -> Vec<String> {
+		let mut result = vec![String::new()];
+		let mut last = vec![String::new()];
+		for _ in 0..max_len {
+			last = last.iter().flat_map(|w| alphabet.chars().map(move |c| format!("{}{}",w,c))).collect();
+			result.extend(last.iter().cloned());
+		}
+		return result;
+	}
+
+	fn unmerged_nfa(regex:&Regex) -> NFA {
+		let letters = regex.alphabet.chars().count();
+		let mut states:Vec<NFAState> = Vec::new();
+		let (start,end) = unmerged(regex.tree.as_ref().unwrap(),&mut states,letters);
+		states[end].accepting = true;
+		return NFA::new(states,start,regex.alphabet.clone());
+	}
+
+	#[test]
+	fn merged_thompson_state_counts() {
+		let cases = [
+			("a",2),
+			("ab",3),
+			("a|b",3),
+			("a*",2),
+			("a+",2),
+			("a?",2),
+			("(a|b)*ab",6),
+			("(ab)*|c",5)
+		];
+		for (regex,count) in cases {
+			assert_eq!(NFA::from(&parse(regex)).states.len(),count,"{}",regex);
+		}
+	}
+
+	#[test]
+	fn merged_thompson_with_non_ascii_alphabet() {
+		let regex = Regex::parse("(é|b)*é?|(éb)+",Some("éb"),false).unwrap();
+		let nfa = NFA::from(&regex);
+		assert!(nfa.states.iter().all(|s| s.transitions.len() == 3));
+		let dfa = DFA::from(&nfa);
+		let unmerged = DFA::from(&unmerged_nfa(&regex));
+		let read = DFA::try_from(dfa.to_string().lines().map(String::from).collect::<Vec<String>>()).unwrap();
+		for word in words("éb",5) {
+			let accepted = matches!(dfa.run(&word),crate::Rslt::Acc);
+			assert_eq!(accepted,matches!(unmerged.run(&word),crate::Rslt::Acc),"{:?}",word);
+			assert_eq!(accepted,matches!(read.run(&word),crate::Rslt::Acc),"{:?}",word);
+		}
+	}
+
+	#[test]
+	fn merged_thompson_keeps_the_language() {
+		let cases = [
+			"a","ab","a|b","a*","a+","a?","(a|b)*ab","(ab)*|c",
+			"(a*b*)*","(a?b)+","a*|b*","((a|b)?c)*","(a*)*","(a+)?","a*b?c+",
+			"(a|b*)(c|a*)","((ab)*|(ba)+)?a","(a|b)*(a|b)+","a(b|c*)*d?"
+		];
+		for regex in cases {
+			let regex = parse(regex);
+			let merged = DFA::from(&NFA::from(&regex));
+			let unmerged = DFA::from(&unmerged_nfa(&regex));
+			for word in words(&regex.alphabet,6) {
+				let accepted = matches!(merged.run(&word),crate::Rslt::Acc);
+				assert_eq!(accepted,matches!(unmerged.run(&word),crate::Rslt::Acc),"{} on {:?}",regex,word);
+			}
+		}
+	}
+}
@@ -0,0 +1,110 @@
+use crate::StateNum;
+use crate::Ordered;
+use crate::nfa::NFA;
+use crate::nfa::NFAState;
+
+/*
+Operations on the languages of automata. The automata may have different alphabets, in which case both are first extended to the union of the alphabets, keeping the letters of the first automaton in order followed by the new letters of the second.
+*/
+
+impl NFA {
+	/// The same NFA over a larger alphabet, which must contain every letter of the current one. The new letters have no transitions.
+	pub fn with_alphabet(&self, alphabet:&str) -> NFA {
+		let index = crate::get_alphabet_hm(alphabet);
+		let states = self.states.iter().map(|s| {
+			let mut transitions:Vec<Ordered> = vec![Ordered(Vec::new());index.len()+1];
+			transitions[0] = s.transitions[0].clone();
+			for (i,letter) in self.alphabet.chars().enumerate() {
+				transitions[index[&letter].0+1] = s.transitions[i+1].clone();
+			}
+			NFAState::new(transitions,s.accepting)
+		}).collect();
+		return NFA::new(states,self.starting,alphabet.to_string());
+	}
+
+	/// Both NFAs over the union of their alphabets.
+	pub fn unify_alphabets(r1:&NFA, r2:&NFA) -> (NFA,NFA) {
+		let mut alphabet = r1.alphabet.clone();
+		for c in r2.alphabet.chars() {
+			if !alphabet.contains(c) {
+				alphabet.push(c);
+			}
+		}
+		return (r1.with_alphabet(&alphabet),r2.with_alphabet(&alphabet));
+	}
+
+	/// Accepts a word of this language followed by a word of the other.
+	pub fn concatenation(&self, other:&NFA) -> NFA {
+		let (mut r1,mut r2) = NFA::unify_alphabets(self,other);
+		return match NFA::concat(&mut r1,&mut r2) {
+			Ok(result) => result,
+			Err(e) => unreachable!("the alphabets were unified, but {}",e)
+		};
+	}
+
+	/// Accepts the words of either language.
+	pub fn union(&self, other:&NFA) -> NFA {
+		let (mut r1,mut r2) = NFA::unify_alphabets(self,other);
+		return match NFA::or(&mut r1,&mut r2) {
+			Ok(result) => result,
+			Err(e) => unreachable!("the alphabets were unified, but {}",e)
+		};
+	}
+
+	/// The shuffle (interleaving) of the two languages: the words formed by interleaving a word of each. Each state of the result is a pair of states, of which either one moves on each letter or jump.
+	pub fn shuffle(&self, other:&NFA) -> NFA {
+		let (r1,r2) = NFA::unify_alphabets(self,other);
+		let m = r2.states.len();
+		let mut states:Vec<NFAState> = Vec::new();
+		for q in 0..r1.states.len() {
+			for p in 0..m {
+				let transitions = (0..r1.states[q].transitions.len()).map(|letter| {
+					let first:Vec<StateNum> = r1.states[q].transitions[letter].0.iter().map(|q2| q2*m+p).collect();
+					let second:Vec<StateNum> = r2.states[p].transitions[letter].0.iter().map(|p2| q*m+p2).collect();
+					Ordered(first).join(&Ordered(second))
+				}).collect();
+				states.push(NFAState::new(transitions,r1.states[q].accepting && r2.states[p].accepting));
+			}
+		}
+		return NFA::new(states,r1.starting*m+r2.starting,r1.alphabet.clone()).trim();
+	}
+
+	/// Accepts the words of the language written backwards: every edge is turned around, and a new starting state jumps to each accepting state.
+	pub fn reverse(&self) -> NFA {
+		let num_letters = self.states[0].transitions.len();
+		let mut states:Vec<NFAState> = self.states.iter().map(|_| NFAState::new(vec![Ordered(Vec::new());num_letters],false)).collect();
+		for (from,s) in self.states.iter().enumerate() {
+			for (letter,t) in s.transitions.iter().enumerate() {
+				for to in &t.0 {
+					states[*to].transitions[letter].0.push(from);
+				}
+			}
+		}
+		states[self.starting].accepting = true;
+		let mut transitions = vec![Ordered(Vec::new());num_letters];
+		transitions[0] = Ordered((0..self.states.len()).filter(|s| self.states[*s].accepting).collect());
+		states.push(NFAState::new(transitions,false));
+		return NFA::new(states,self.states.len(),self.alphabet.clone());
+	}
+
+	/// Accepts any number of words of the language one after the other, including none.
+	pub fn star(&self) -> NFA {
+		let mut result = self.clone();
+		result.make_kstar();
+		return result;
+	}
+
+	/// Accepts one or more words of the language one after the other.
+	pub fn plus(&self) -> NFA {
+		let mut result = self.clone();
+		result.make_kplus();
+		return result;
+	}
+
+	/// Accepts the words of the language and the empty word.
+	pub fn optional(&self) -> NFA {
+		let mut result = self.clone();
+		result.make_optional();
+		return result;
+	}
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::StateNum;
+use crate::Ordered;
+use crate::regex::Regex;
+use crate::regex::RegexTree;
+use crate::nfa::NFA;
+use crate::nfa::NFAState;
+use crate::derivative::make_concat;
+use crate::derivative::make_repeat;
+use crate::derivative::make_star;
+use crate::derivative::make_shuffle;
+
+use crate::limits::Budget;
+use crate::limits::LimitError;
+use crate::limits::Progress;
+
+use crate::Index0;
+use crate::Index1;
+
+/*
+Antimirov's partial derivatives. Where the Brzozowski derivative of a regex by a letter is a single regex, the partial derivative is a set of regexes whose alternation is the derivative. Each one is a concatenation of subexpressions of the original regex, so there are at most n+1 distinct ones for a regex with n letters, and they are the states of an NFA without jumps.
+*/
+
+/// The set of regexes each term is concatenated with.
+fn concat_each(terms:Vec<RegexTree>, r:&RegexTree) -> Vec<RegexTree> {
+	return terms.into_iter()
+		.filter_map(|t| make_concat(Some(t),Some(r.clone())))
+		.collect();
+}
+
+fn union(mut r1:Vec<RegexTree>, r2:Vec<RegexTree>) -> Vec<RegexTree> {
+	for r in r2 {
+		if !r1.contains(&r) {
+			r1.push(r);
+		}
+	}
+	return r1;
+}
+
+impl RegexTree {
+	pub fn partial_derivative(&self, letter:Index0) -> Vec<RegexTree> {
+		return match self {
+			Self::Empty => Vec::new(),
+			Self::Single(i) => match *i == letter {
+				true => vec![Self::Empty],
+				false => Vec::new()
+			},
+			Self::Or((r1,r2)) => union(r1.partial_derivative(letter),r2.partial_derivative(letter)),
+			Self::Concat((r1,r2)) => {
+				let first = concat_each(r1.partial_derivative(letter),r2);
+				match r1.nullable() {
+					true => union(first,r2.partial_derivative(letter)),
+					false => first
+				}
+			},
+			Self::Shuffle((r1,r2)) => {
+				let first:Vec<RegexTree> = r1.partial_derivative(letter).into_iter()
+					.filter_map(|d| make_shuffle(Some(d),Some((**r2).clone())))
+					.collect();
+				let second:Vec<RegexTree> = r2.partial_derivative(letter).into_iter()
+					.filter_map(|d| make_shuffle(Some((**r1).clone()),Some(d)))
+					.collect();
+				union(first,second)
+			},
+			Self::KleeneStar(r) | Self::KleenePlus(r) => concat_each(r.partial_derivative(letter),&make_star((**r).clone())),
+			Self::QMark(r) => r.partial_derivative(letter),
+			Self::Repeat{max:Some(0),..} => Vec::new(),
+			Self::Repeat{tree,min,max} => {
+				let rest = make_repeat((**tree).clone(),min.saturating_sub(1),max.map(|m| m-1));
+				concat_each(tree.partial_derivative(letter),&rest)
+			}
+		};
+	}
+}
+
+impl NFA {
+	/// Builds an NFA without jumps whose states are the partial derivatives of the regex, stopping if it takes longer than the limit.
+	pub fn from_partial_derivatives(regex:&Regex, budget:&Budget) -> Result<NFA,LimitError> {
+		// written in the same normal form as the terms, so it is not repeated as a separate state
+		let tree = match make_concat(regex.tree.clone(),Some(RegexTree::Empty)) {
+			None => return Ok(NFA::get_never_accept(regex.alphabet.clone())),
+			Some(t) => t
+		};
+		let num_letters = regex.alphabet.chars().count();
+		let mut new_states:HashMap<RegexTree,StateNum> = HashMap::new();
+		let mut terms:Vec<RegexTree> = Vec::new();
+		let mut frontier:VecDeque<StateNum> = VecDeque::new();
+		let mut transitions:Vec<Vec<Ordered>> = Vec::new();
+
+		new_states.insert(tree.clone(),0);
+		terms.push(tree);
+		frontier.push_back(0);
+		transitions.push(vec![Ordered(Vec::new());num_letters+1]);
+		while let Some(current) = frontier.pop_front() {
+			if let Err(e) = budget.check_time(Progress::Building{states:terms.len()}) {
+				return Err(e);
+			}
+			for letter in 0..num_letters {
+				let mut next_states:Vec<StateNum> = Vec::new();
+				for next in terms[current].partial_derivative(Index0(letter)) {
+					if !new_states.contains_key(&next) {
+						new_states.insert(next.clone(),terms.len());
+						frontier.push_back(terms.len());
+						terms.push(next.clone());
+						transitions.push(vec![Ordered(Vec::new());num_letters+1]);
+					}
+					next_states.push(new_states[&next]);
+				}
+				next_states.sort();
+				let letter:Index1 = Index0(letter).into();
+				transitions[current][letter.0] = Ordered(next_states);
+			}
+		}
+
+		let states:Vec<NFAState> = transitions.into_iter()
+			.zip(terms.iter())
+			.map(|(t,term)| NFAState::new(t,term.nullable()))
+			.collect();
+		return Ok(NFA::new(states,0,regex.alphabet.clone()));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dfa::DFA;
+	use crate::limits::Limits;
+
+	/// Has the same language as Thompson's construction, and at most one more state than the regex has positions, which is the size of its Glushkov automaton.
+	#[test]
+	fn same_language_as_thompson() {
+		let cases = [
+			"a","ε","\\0","ab|ba","(a|b)*abb","(ab)*|c+","a*b*c*","((a|b)c)*a?",
+			"a{3}","a{2,4}b","(ab){1,3}|c{2,}","(a{0,2}b?){2}","(a|b){2,}c","(a|ε)(b*|ca)*"
+		];
+		for regex in cases {
+			let regex = Regex::parse(regex,Some("abc"),false).unwrap();
+			let antimirov = NFA::from_partial_derivatives(&regex,&Limits::default().start()).unwrap();
+			let expected = DFA::from(&NFA::from(&regex));
+			assert_eq!(DFA::from(&antimirov).distinguishing_word(&expected),None,"{}",regex);
+			let (glushkov,_) = NFA::from_glushkov(&regex,&Limits::default().start()).unwrap();
+			assert!(antimirov.states.len() <= glushkov.states.len(),"{}",regex);
+		}
+	}
+}
@@ -0,0 +1,179 @@
+use crate::StateNum;
+use crate::Ordered;
+use crate::nfa::NFA;
+use crate::nfa::NFAState;
+use crate::state_set::StateSet;
+
+/*
+Closures and quotients of the language of an NFA, found by searching the graph of the NFA, or for quotients the graph of its product with the NFA of the other language. A state (q,p) of the product is numbered q*m+p, where m is the number of states of the other NFA, and can move on a jump of either NFA, or on a letter both NFAs move on.
+*/
+
+impl NFA {
+	/// The states, letters and jumps included, that can be reached from the starting state.
+	fn reachable_states(&self) -> StateSet {
+		return StateSet::reachable(self.states.len(),&[self.starting],|state| {
+			self.states[state].transitions.iter().flat_map(|t| t.0.iter().cloned()).collect::<Vec<StateNum>>()
+		});
+	}
+
+	/// The states from which an accepting state can be reached.
+	fn live_states(&self) -> StateSet {
+		let predecessors = self.predecessors();
+		let accepting:Vec<StateNum> = (0..self.states.len()).filter(|s| self.states[*s].accepting).collect();
+		return StateSet::reachable(self.states.len(),&accepting,|state| predecessors[state].clone());
+	}
+
+	fn predecessors(&self) -> Vec<Vec<StateNum>> {
+		let mut predecessors:Vec<Vec<StateNum>> = vec![Vec::new();self.states.len()];
+		for (state,s) in self.states.iter().enumerate() {
+			for t in &s.transitions {
+				for next in &t.0 {
+					predecessors[*next].push(state);
+				}
+			}
+		}
+		return predecessors;
+	}
+
+	/// Adds a new starting state with jumps to the given states.
+	fn start_from_any(&mut self, starts:Vec<StateNum>) {
+		let mut transitions:Vec<Ordered> = vec![Ordered(Vec::new());self.states[0].transitions.len()];
+		transitions[0] = Ordered(starts);
+		self.starting = self.states.len();
+		self.states.push(NFAState::new(transitions,false));
+	}
+
+	/// The same NFA without the states that are unreachable or cannot reach an accepting state. The starting state is always kept.
+	pub fn trim(&self) -> NFA {
+		let reachable = self.reachable_states();
+		let live = self.live_states();
+		let mut number:Vec<Option<StateNum>> = vec![None;self.states.len()];
+		let mut kept:Vec<StateNum> = Vec::new();
+		for (s,n) in number.iter_mut().enumerate() {
+			if s == self.starting || (reachable.contains(s) && live.contains(s)) {
+				*n = Some(kept.len());
+				kept.push(s);
+			}
+		}
+		let states = kept.iter().map(|s| {
+			let transitions = self.states[*s].transitions.iter()
+				.map(|t| Ordered(t.0.iter().filter_map(|n| number[*n]).collect()))
+				.collect();
+			NFAState::new(transitions,self.states[*s].accepting)
+		}).collect();
+		return NFA::new(states,number[self.starting].unwrap(),self.alphabet.clone());
+	}
+
+	/// Accepts every prefix of a word of the language.
+	pub fn prefix_closure(&self) -> NFA {
+		let live = self.live_states();
+		let mut result = self.clone();
+		for state in live.iter() {
+			result.states[state].accepting = true;
+		}
+		return result;
+	}
+
+	/// Accepts every suffix of a word of the language.
+	pub fn suffix_closure(&self) -> NFA {
+		let mut result = self.clone();
+		result.start_from_any(self.reachable_states().iter().collect());
+		return result;
+	}
+
+	/// Accepts every factor (infix) of a word of the language.
+	pub fn infix_closure(&self) -> NFA {
+		return self.prefix_closure().suffix_closure();
+	}
+
+	/// The product of the two NFAs, as the successors of each state of the product.
+	fn product(&self, other:&NFA) -> Vec<Vec<StateNum>> {
+		let m = other.states.len();
+		let mut successors:Vec<Vec<StateNum>> = vec![Vec::new();self.states.len()*m];
+		for q in 0..self.states.len() {
+			for p in 0..m {
+				let next = &mut successors[q*m+p];
+				let (from_q,from_p) = (&self.states[q].transitions,&other.states[p].transitions);
+				next.extend(from_q[0].0.iter().map(|q2| q2*m+p));
+				next.extend(from_p[0].0.iter().map(|p2| q*m+p2));
+				for letter in 1..from_q.len() {
+					for q2 in &from_q[letter].0 {
+						next.extend(from_p[letter].0.iter().map(|p2| q2*m+p2));
+					}
+				}
+			}
+		}
+		return successors;
+	}
+
+	/// The right quotient L/K by the language K of the other NFA: the words w such that wv is in L for some v in K.
+	pub fn right_quotient(&self, other:&NFA) -> NFA {
+		let (mut result,other) = NFA::unify_alphabets(self,other);
+		let m = other.states.len();
+		let successors = result.product(&other);
+		let mut predecessors:Vec<Vec<StateNum>> = vec![Vec::new();successors.len()];
+		for (state,next) in successors.iter().enumerate() {
+			for n in next {
+				predecessors[*n].push(state);
+			}
+		}
+		let accepting:Vec<StateNum> = (0..successors.len())
+			.filter(|s| result.states[s/m].accepting && other.states[s%m].accepting)
+			.collect();
+		let live = StateSet::reachable(successors.len(),&accepting,|state| predecessors[state].clone());
+		for q in 0..result.states.len() {
+			result.states[q].accepting = live.contains(q*m+other.starting);
+		}
+		return result;
+	}
+
+	/// The left quotient K\L by the language K of the other NFA: the words w such that vw is in L for some v in K.
+	pub fn left_quotient(&self, other:&NFA) -> NFA {
+		let (mut result,other) = NFA::unify_alphabets(self,other);
+		let m = other.states.len();
+		let successors = result.product(&other);
+		let reached = StateSet::reachable(successors.len(),&[result.starting*m+other.starting],|state| successors[state].clone());
+		let mut starts:Vec<StateNum> = reached.iter()
+			.filter(|s| other.states[s%m].accepting)
+			.map(|s| s/m)
+			.collect();
+		starts.sort();
+		starts.dedup();
+		result.start_from_any(starts);
+		return result;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::dfa::DFA;
+	use crate::nfa::NFA;
+	use crate::regex::Regex;
+
+	fn nfa(regex:&str) -> NFA {
+		return NFA::from(&Regex::parse(regex,Some("abcd"),false).unwrap());
+	}
+
+	fn assert_same(result:&NFA, expected:&str) {
+		let expected = DFA::from(&nfa(expected));
+		assert_eq!(DFA::from(result).distinguishing_word(&expected),None,"{}",expected);
+	}
+
+	#[test]
+	fn right_quotients() {
+		assert_same(&nfa("(ab)*").right_quotient(&nfa("b")),"(ab)*a");
+		assert_same(&nfa("(ab)*").right_quotient(&nfa("a")),"\\0");
+		assert_same(&nfa("abc|bbc|abd").right_quotient(&nfa("c")),"ab|bb");
+		assert_same(&nfa("a*ba*").right_quotient(&nfa("a*")),"a*ba*");
+		assert_same(&nfa("abcd").right_quotient(&nfa("d|cd|ε")),"abc|ab|abcd");
+	}
+
+	#[test]
+	fn left_quotients() {
+		assert_same(&nfa("(ab)*").left_quotient(&nfa("a")),"b(ab)*");
+		assert_same(&nfa("(ab)*").left_quotient(&nfa("b")),"\\0");
+		assert_same(&nfa("abc|abd|bbc").left_quotient(&nfa("ab")),"c|d");
+		assert_same(&nfa("a*ba*").left_quotient(&nfa("a*b")),"a*");
+		assert_same(&nfa("abcd").left_quotient(&nfa("a|ab|ε")),"bcd|cd|abcd");
+	}
+}
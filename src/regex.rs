@@ -1,10 +1,23 @@
 use std::fmt;
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
 use crate::nfa::NFA;
+use crate::int_nfa_reg::RegexOptions;
+use crate::limits::Budget;
+use crate::limits::Limits;
+use crate::limits::LimitError;
+use crate::limits::Progress;
 
 use std::convert::From;
 use std::convert::TryFrom;
 
+use crate::regex_parser;
+use crate::regex_parser::Lexeme;
+use crate::regex_parser::Parser;
+use crate::regex_parser::Token;
+
 use crate::Index0;
 
 #[derive(Clone,Debug)]
@@ -14,102 +27,131 @@ pub struct Regex {
 }
 
 impl Regex {
-	//valid characters are (,),|,+,?,*, and all lowercase ascii letters other than ':' or ','	
-	pub const VALID_SYMBOLS:[char;6] = ['(',')','|','+','?','*'];
+	/// Characters with a special meaning in regexes. They can still be used as letters by escaping them with '\\'.
+	pub const SPECIAL_SYMBOLS:[char;15] = ['(',')','|','%','+','?','*','[',']','.','\\','{','}','ε','∅'];
 
 	pub fn new(alphabet:String, tree:Option<RegexTree>) -> Self {
 		Self{alphabet,tree}
 	}
-	fn validate_regex(regex:&Vec<char>) -> Result<String,String> {
-		let mut alphabet:Vec<char> = Vec::new();
+
+	/// Parses a regex over the declared alphabet, or over the letters appearing in it if there is none.
+	/// Lenient parsing accepts malformed regexes, interpreting them as earlier versions of the program did: missing operands of operators are read as the empty word.
+	pub fn parse(regex_in:&str, declared:Option<&str>, lenient:bool) -> Result<Self,String> {
+		let declared = match declared {
+			None => None,
+			Some(d) => match crate::get_alphabet(d) {
+				Err(e) => return Err(e),
+				Ok(ab) => Some(ab)
+			}
+		};
+		let regex:Vec<char>=regex_in.chars().collect();
+		let tokens:Vec<Token> = match regex_parser::lex(&regex) {
+			Err(e) => return Err(format!("Invalid regex. {}",e)),
+			Ok(t) => t
+		};
+		let ast = match lenient {
+			true => match Self::check_brackets(&tokens) {
+				Err(e) => return Err(format!("Invalid regex. {}",e)),
+				Ok(()) => None
+			},
+			false => match Parser::parse(&tokens) {
+				Err(e) => return Err(format!("Invalid regex. {}",e)),
+				Ok(a) => Some(a)
+			}
+		};
+		let alphabet: String =	match regex_parser::get_regex_alphabet(&tokens,declared.as_deref()) {
+			Err(e) => return Err(format!("Invalid regex. {}",e)),
+			Ok(a) => a
+		};
+		let alphabet = match crate::get_alphabet(&alphabet) {
+			Err(e) => return Err(e),
+			Ok(ab) => ab
+		};
+		let alphabet_hashmap = crate::get_alphabet_hm(&alphabet);
+		let tree = match ast {
+			Some(ast) => ast.to_tree(&alphabet,&alphabet_hashmap),
+			None => {
+				let tokens:Vec<InProgress> = tokens.iter()
+					.map(|t| InProgress::from_lexeme(&t.lexeme,&alphabet,&alphabet_hashmap))
+					.collect();
+				RegexTree::from_tokens(tokens)
+			}
+		};
+		return Ok(Self::new(alphabet, tree));
+	}
+
+	fn check_brackets(regex:&Vec<Token>) -> Result<(),String> {
 		let mut depth=0;
-		for c in regex {
-			if *c == '(' {
-				depth += 1;
-			} else if *c == ')' {
-				depth -= 1;
+		for token in regex {
+			match token.lexeme {
+				Lexeme::Operator('(') => depth += 1,
+				Lexeme::Operator(')') => depth -= 1,
+				_ => ()
+			}
+			if depth == -1 {
+				return Err("There is a closing bracket with no matching opening bracket".to_string());
 			}
-		if depth == -1 {
-			return Err("There is a closing bracket with no matching opening bracket".to_string());
-		}
-		if !(Self::VALID_SYMBOLS.contains(c)||alphabet.contains(c)) {
-			alphabet.push(*c);
-		}
 		}
 		if depth != 0 {
 			return Err("There are opening brackets that are not closed".to_string());
 		}
-		return Ok(alphabet.iter().cloned().collect());
+		return Ok(());
 	}
 
 }
 
 impl TryFrom<String> for Regex {
 	type Error = String;
-	
+
 	fn try_from(regex_in:String) -> Result<Self,Self::Error> {
-		let regex:Vec<char>=regex_in.chars().collect();
-		let alphabet: String =	match Self::validate_regex(&regex) {
-			Err(e) => return Err(format!("Invalid regex. {}",e)),
-			Ok(a) => a
-		};
-		let alphabet = match crate::get_alphabet(&alphabet) {
-			Err(e) => return Err(e),
-			Ok(ab) => ab
-		};
-		let alphabet_hashmap = crate::get_alphabet_hm(&alphabet);
-		let regex:Vec<InProgress> = regex
-			.iter()
-			.map(|c| InProgress::from_char(*c,&alphabet_hashmap)).collect();
-		
-		let regex = RegexTree::from(regex);
-		return Ok(Self::new(alphabet, Some(regex)));
+		return Self::try_from((regex_in,None));
 	}
 }
 
+/// Parses a regex over an explicitly declared alphabet. When the alphabet is `None` it is inferred from the letters appearing in the regex.
+impl TryFrom<(String,Option<String>)> for Regex {
+	type Error = String;
+	
+	fn try_from((regex_in,declared):(String,Option<String>)) -> Result<Self,Self::Error> {
+		return Self::parse(&regex_in,declared.as_deref(),false);
+	}
+}
+
+/// Converts by state elimination in the weight order, simplifying the result. Use `int_nfa_reg::convert` to choose other options.
 impl From<&NFA> for Regex {
 	fn from(nfa:&NFA) -> Self {
-		return crate::int_nfa_reg::nfa_to_regex(nfa);
+		return match crate::int_nfa_reg::convert(nfa,&RegexOptions::default(),&Limits::default()) {
+			Ok(regex) => regex,
+			Err(e) => unreachable!("no limits were set, but {}",e)
+		};
+	}
+}
+
+impl Regex {
+	/// Writes the regex out, stopping if that takes longer than the time limit.
+	pub fn to_string_within(&self, limits:&Limits) -> Result<String,LimitError> {
+		let alphabet:Vec<char> = self.alphabet.chars().collect();
+		return match &self.tree {
+			None => Ok("∅".to_string()),
+			Some(tree) => tree.to_string(&alphabet,&limits.start())
+		};
 	}
 }
 
 impl fmt::Display for Regex {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let alphabet = &self.alphabet;
-		let output = match &self.tree {
-			None => String::new(),
-			Some(tree) => tree.to_string(&alphabet.chars().collect())
+		let output = match self.to_string_within(&Limits::default()) {
+			Ok(output) => output,
+			Err(e) => unreachable!("no limits were set, but {}",e)
 		};
 		write!(f,"{}",output)
 	}
 }
 
-/*
-fn simplify_regex(mut regex: Regex) -> Regex {//this step isn't strictly necessary, but it should simplify the resultant NFA
-    let mut changed = true;
-    while changed {
-	changed = false;
-	match regex {
-	    RegexTree::Empty => (),
-	    RegexTree::Single(_) => (), //could put these together at the default case, but this nested match is complicated enough that I want each option addressed explicitly
-	    RegexTree::KleeneStar(br) => {
-		match *br {
-		    RegexTree::Empty => {
-			input = RegexTree::Empty;
-			changed = true;
-		    },
-		    RegexTree::Single(_) => (),
-		    RegexTree::KleeneStar
-		    
-		} 
-	    }
-	}
-    }
-    return regex
-}
-*/
-fn get_kstar(r:&RegexTree, alphabet:String) -> Result<NFA,String> {
-    let mut sub = match r.to_nfa(alphabet) {
+const SAME_ALPHABET:&str = "This only fails if two generated alphabets are different, which indicates a programming error, not a user error";
+
+fn get_kstar(r:&RegexTree, alphabet:String, budget:&Budget) -> Result<NFA,LimitError> {
+    let mut sub = match r.to_nfa(alphabet,budget) {
 		Ok(r) => r,
 		Err(e) => return Err(e)
 	};
@@ -117,50 +159,145 @@ fn get_kstar(r:&RegexTree, alphabet:String) -> Result<NFA,String> {
     return Ok(sub);
 }
 
-fn get_concat(r1:&RegexTree, r2:&RegexTree, alphabet:String) -> Result<NFA,String> {
-    let mut r1 = match r1.to_nfa(alphabet.clone()) {
+fn get_kplus(r:&RegexTree, alphabet:String, budget:&Budget) -> Result<NFA,LimitError> {
+	let mut sub = match r.to_nfa(alphabet,budget) {
 		Ok(r) => r,
 		Err(e) => return Err(e)
 	};
-    let mut r2 = match r2.to_nfa(alphabet) {
+	sub.make_kplus();
+	return Ok(sub);
+}
+
+fn get_qmark(r:&RegexTree, alphabet:String, budget:&Budget) -> Result<NFA,LimitError> {
+	let mut sub = match r.to_nfa(alphabet,budget) {
 		Ok(r) => r,
 		Err(e) => return Err(e)
 	};
-	return NFA::concat(&mut r1,&mut r2);
+	sub.make_optional();
+	return Ok(sub);
 }
 
-fn get_or(r1:&RegexTree, r2:&RegexTree, alphabet:String) -> Result<NFA,String> {
-	    let mut r1 = match r1.to_nfa(alphabet.clone()) {
+fn get_concat(r1:&RegexTree, r2:&RegexTree, alphabet:String, budget:&Budget) -> Result<NFA,LimitError> {
+    let mut r1 = match r1.to_nfa(alphabet.clone(),budget) {
 		Ok(r) => r,
 		Err(e) => return Err(e)
 	};
-    let mut r2 = match r2.to_nfa(alphabet.clone()) {
+    let mut r2 = match r2.to_nfa(alphabet,budget) {
 		Ok(r) => r,
 		Err(e) => return Err(e)
 	};
-	return NFA::or(&mut r1,&mut r2);
+	return Ok(NFA::concat(&mut r1,&mut r2).expect(SAME_ALPHABET));
 }
 
-#[derive(Clone,Debug)]
+fn get_or(r1:&RegexTree, r2:&RegexTree, alphabet:String, budget:&Budget) -> Result<NFA,LimitError> {
+	    let mut r1 = match r1.to_nfa(alphabet.clone(),budget) {
+		Ok(r) => r,
+		Err(e) => return Err(e)
+	};
+    let mut r2 = match r2.to_nfa(alphabet.clone(),budget) {
+		Ok(r) => r,
+		Err(e) => return Err(e)
+	};
+	return Ok(NFA::or(&mut r1,&mut r2).expect(SAME_ALPHABET));
+}
+
+fn get_shuffle(r1:&RegexTree, r2:&RegexTree, alphabet:String, budget:&Budget) -> Result<NFA,LimitError> {
+	let r1 = match r1.to_nfa(alphabet.clone(),budget) {
+		Ok(r) => r,
+		Err(e) => return Err(e)
+	};
+	let r2 = match r2.to_nfa(alphabet,budget) {
+		Ok(r) => r,
+		Err(e) => return Err(e)
+	};
+	return Ok(r1.shuffle(&r2));
+}
+
+fn get_repeat(r:&RegexTree, min:usize, max:Option<usize>, alphabet:String, budget:&Budget) -> Result<NFA,LimitError> {
+	let parts = match r.repeat_parts(min,max,budget) {
+		Ok(p) => p,
+		Err(e) => return Err(e)
+	};
+	let mut result = NFA::get_accept_empty(alphabet.clone()).expect(SAME_ALPHABET);
+	for part in &parts {
+		if let Err(e) = budget.check_time(Progress::Expanding{copies:parts.len()}) {
+			return Err(e);
+		}
+		let mut next = match part.to_nfa(alphabet.clone(),budget) {
+			Ok(r) => r,
+			Err(e) => return Err(e)
+		};
+		result = NFA::concat(&mut result,&mut next).expect(SAME_ALPHABET);
+	}
+	return Ok(result);
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Hash,PartialOrd,Ord)]
 pub enum RegexTree {
     Empty,
     Single(Index0),
     KleeneStar(Box<RegexTree>),
     KleenePlus(Box<RegexTree>),
     QMark(Box<RegexTree>),
+    Repeat{tree:Box<RegexTree>, min:usize, max:Option<usize>}, // max of None is unbounded
     Concat((Box<RegexTree>,Box<RegexTree>)),
+    Shuffle((Box<RegexTree>,Box<RegexTree>)), // the interleavings of a word of each
     Or((Box<RegexTree>,Box<RegexTree>)),
 }
 impl RegexTree {
-	pub fn to_nfa(&self,a:String) -> Result<NFA,String> {
+	/// The regexes that r{n,m} is the concatenation of: n copies of r followed by m-n copies of r?, or for r{n,} n copies of r followed by r*. The size of the copies is checked against the limit before any are made.
+	pub fn repeat_parts(&self, min:usize, max:Option<usize>, budget:&Budget) -> Result<Vec<RegexTree>,LimitError> {
+		let copies = max.unwrap_or(min.saturating_add(1));
+		let progress = Progress::Expanding{copies};
+		if let Err(e) = budget.check_regex_size(|| copies.saturating_mul(self.size()+2),progress) {
+			return Err(e);
+		}
+		let mut parts:Vec<RegexTree> = Vec::new();
+		for i in 0..copies {
+			if let Err(e) = budget.check_time(progress) {
+				return Err(e);
+			}
+			parts.push(match (i < min,max) {
+				(true,_) => self.clone(),
+				(false,None) => RegexTree::KleeneStar(Box::new(self.clone())),
+				(false,Some(_)) => RegexTree::QMark(Box::new(self.clone()))
+			});
+		}
+		return Ok(parts);
+	}
+
+	/// Whether the regex accepts the empty word.
+	pub fn nullable(&self) -> bool {
+		return match self {
+			Self::Empty | Self::KleeneStar(_) | Self::QMark(_) => true,
+			Self::Single(_) => false,
+			Self::KleenePlus(r) => r.nullable(),
+			Self::Repeat{tree,min,..} => *min == 0 || tree.nullable(),
+			Self::Concat((r1,r2)) | Self::Shuffle((r1,r2)) => r1.nullable() && r2.nullable(),
+			Self::Or((r1,r2)) => r1.nullable() || r2.nullable()
+		};
+	}
+
+	/// The number of nodes in the tree.
+	pub fn size(&self) -> usize {
+		return match self {
+			Self::Empty | Self::Single(_) => 1,
+			Self::KleeneStar(r) | Self::KleenePlus(r) | Self::QMark(r) | Self::Repeat{tree:r,..} => 1 + r.size(),
+			Self::Concat((r1,r2)) | Self::Shuffle((r1,r2)) | Self::Or((r1,r2)) => 1 + r1.size() + r2.size()
+		};
+	}
+
+	pub fn to_nfa(&self,a:String,budget:&Budget) -> Result<NFA,LimitError> {
 		return match &self {
-			Self::Empty => NFA::get_accept_empty(a),
-			Self::Single(i) => NFA::get_accept_single((*i).into(),a),
-			Self::KleeneStar(r) => get_kstar(&*r, a),
-			Self::KleenePlus(r) => get_concat(&(**r).clone(),&Self::KleeneStar(Box::new((**r).clone())),a),
-			Self::QMark(r) => get_or(&Self::Empty,&**r,a),
-			Self::Concat((r1,r2)) => get_concat(&**r1, &**r2,a),
-			Self::Or((r1, r2)) => get_or(&**r1,&**r2,a)
+			Self::Empty => Ok(NFA::get_accept_empty(a).expect(SAME_ALPHABET)),
+			Self::Single(i) => Ok(NFA::get_accept_single((*i).into(),a).expect(SAME_ALPHABET)),
+			Self::KleeneStar(r) => get_kstar(r, a,budget),
+			Self::KleenePlus(r) => get_kplus(r, a,budget),
+			Self::QMark(r) => get_qmark(r, a,budget),
+			Self::Repeat{tree,min,max} => get_repeat(tree,*min,*max,a,budget),
+			Self::Concat((r1,r2)) => get_concat(r1, r2,a,budget),
+			Self::Shuffle((r1,r2)) => get_shuffle(r1,r2,a,budget),
+			Self::Or((r1, r2)) => get_or(r1,r2,a,budget)
 		};
 	}
 
@@ -174,30 +311,36 @@ impl RegexTree {
 						r => sub_bracket.push(r)
 					}
 				}
-				input[start] = InProgress::Reg(Self::from(sub_bracket));
+				input[start] = InProgress::Reg(Self::from_tokens(sub_bracket));
 			}
 		}
 	}
 
-	fn add_unary(input:&mut Vec<InProgress>, i:usize, f:fn(Box<Self>) -> Self) {
+	fn add_unary(input:&mut Vec<InProgress>, i:usize, f:impl Fn(Option<Self>) -> Option<Self>) -> usize {
 		if i == 0 {
-			input[0] = InProgress::Reg(Self::Empty);
+			input[0] = InProgress::Reg(Some(Self::Empty));
+			return 1;
 		} else if let InProgress::Reg(r) = &input[i-1] {
-			input[i-1] = InProgress::Reg(f(Box::new(r.clone())));
+			input[i-1] = InProgress::Reg(f(r.clone()));
 			input.remove(i);
+			return i;
 		} else {
-			input[i] = InProgress::Reg(Self::Empty);
+			input[i] = InProgress::Reg(Some(Self::Empty));
+			return i + 1;
 		}
 	}
 	
 	fn process_unary(input:&mut Vec<InProgress>) {
-		for i in (0..input.len()).rev() {
-			match &input[i] {
-				InProgress::KStar => Self::add_unary(input, i, |r| Self::KleeneStar(r)),
-				InProgress::KPlus => Self::add_unary(input, i, |r| Self::KleenePlus(r)),
-				InProgress::QMark => Self::add_unary(input, i, |r| Self::QMark(r)),
-				_ => ()
-			}
+		// left to right, so that stacked postfix operators such as a*{2} apply to the result of the previous one
+		let mut i = 0;
+		while i < input.len() {
+			i = match input[i].clone() {
+				InProgress::KStar => Self::add_unary(input, i, Self::star_opt),
+				InProgress::KPlus => Self::add_unary(input, i, Self::plus_opt),
+				InProgress::QMark => Self::add_unary(input, i, Self::qmark_opt),
+				InProgress::Repeat(min,max) => Self::add_unary(input, i, |r| Self::repeat_opt(r,min,max)),
+				_ => i + 1
+			};
 		}
 	}
 
@@ -205,110 +348,377 @@ impl RegexTree {
 		for i in (1..input.len()).rev() {
 			if let InProgress::Reg(r2) = &input[i] {
 				if let InProgress::Reg(r1) = &input[i-1] {
-					input[i-1] = InProgress::Reg(Self::Concat((Box::new(r1.clone()),Box::new(r2.clone()))));
+					input[i-1] = InProgress::Reg(Self::concat_opt(r1.clone(),r2.clone()));
 					input.remove(i);
 				}
 			}
 		}
 	}
 
+	fn process_shuffle(input:&mut Vec<InProgress>) {
+		// a missing operand is the empty word, which leaves the other operand unchanged
+		let mut i = 0;
+		while i < input.len() {
+			if let InProgress::Shuffle = input[i] {
+				let mut r1 = Some(Self::Empty);
+				if i > 0 {
+					if let InProgress::Reg(temp) = &input[i-1] {
+						r1 = temp.clone();
+						input.remove(i-1);
+						i -= 1;
+					}
+				}
+				let mut r2 = Some(Self::Empty);
+				if i + 1 < input.len() {
+					if let InProgress::Reg(temp) = &input[i+1] {
+						r2 = temp.clone();
+						input.remove(i+1);
+					}
+				}
+				input[i] = InProgress::Reg(Self::shuffle_opt(r1,r2));
+			}
+			i += 1;
+		}
+	}
+
 	fn process_or(input:&mut Vec<InProgress>) {
 		let mut i = 0;
 		while i < input.len() {
 			if let InProgress::Or = input[i] {
 				let r1;
 				if i == 0 {
-					r1 = Self::Empty;
+					r1 = Some(Self::Empty);
 				} else if let InProgress::Reg(temp) = &input[i-1] {
 					r1 = (*temp).clone();
 						input.remove(i);
-					i = i - 1; // so i is still pointing to the Or.
+					i -= 1; // so i is still pointing to the Or.
 				} else {
-					r1 = Self::Empty;//this will never be reached, as all other possible InProgress values have been removed
+					r1 = Some(Self::Empty);//this will never be reached, as all other possible InProgress values have been removed
 					}					
 				let r2;
 				if i == input.len() - 1 {
-					r2 = Self::Empty;
+					r2 = Some(Self::Empty);
 				} else if let InProgress::Reg(temp) = &input[i + 1] {
 					r2 = (*temp).clone();
 					input.remove(i + 1);
 				} else {
-						r2 = Self::Empty;// this could be another Or though
+						r2 = Some(Self::Empty);// this could be another Or though
 				}
-				let new_val = match r1 {
-					Self::Empty => match r2 {
-						Self::Empty => Self::Empty,
-						r => Self::QMark(Box::new(r))
-					},
-					r1 => match r2 {
-						Self::Empty => Self::QMark(Box::new(r1)),
-							r2 => Self::Or((Box::new(r1),Box::new(r2)))
-					}
+				let new_val = match (r1,r2) {
+					(Some(Self::Empty),Some(Self::Empty)) => Some(Self::Empty),
+					(Some(Self::Empty),Some(r)) | (Some(r),Some(Self::Empty)) => Some(Self::QMark(Box::new(r))),
+					(r1,r2) => Self::or_opt(r1,r2)
 				};
 				input[i] = InProgress::Reg(new_val);
 			}
-			i = i + 1;
+			i += 1;
 		}
 	}
 	
-	fn opp_to_string(opp:char, child:&Self,alphabet:&Vec<char>) -> String {//regex is a mix of infix and postfix notation so brackets need to be added where appropriate
-		let mut result = String::new();
-		//need brackets around ors or concats
+	// Operators over regexes that may denote the empty language, written as None.
 
-		match child {
-			Self::Empty => return String::new(),
-			Self::Single(_)|Self::KleeneStar(_)|Self::KleenePlus(_)|Self::QMark(_) => result.push_str(&child.to_string(alphabet)),
-			Self::Concat(_)|Self::Or(_) => {
-				result.push('(');
-				result.push_str(&child.to_string(alphabet));
-				result.push(')');
-			},
+	pub fn concat_opt(r1:Option<Self>, r2:Option<Self>) -> Option<Self> {
+		return match (r1,r2) {
+			(Some(r1),Some(r2)) => Some(Self::Concat((Box::new(r1),Box::new(r2)))),
+			_ => None
+		};
+	}
+
+	pub fn shuffle_opt(r1:Option<Self>, r2:Option<Self>) -> Option<Self> {
+		return match (r1,r2) {
+			(Some(r1),Some(r2)) => Some(Self::Shuffle((Box::new(r1),Box::new(r2)))),
+			_ => None
+		};
+	}
+
+	pub fn or_opt(r1:Option<Self>, r2:Option<Self>) -> Option<Self> {
+		return match (r1,r2) {
+			(Some(r1),Some(r2)) => Some(Self::Or((Box::new(r1),Box::new(r2)))),
+			(None,r) | (r,None) => r
+		};
+	}
+
+	pub fn star_opt(r:Option<Self>) -> Option<Self> {
+		return match r {
+			Some(r) => Some(Self::KleeneStar(Box::new(r))),
+			None => Some(Self::Empty)
+		};
+	}
+
+	pub fn plus_opt(r:Option<Self>) -> Option<Self> {
+		return r.map(|r| Self::KleenePlus(Box::new(r)));
+	}
+
+	pub fn qmark_opt(r:Option<Self>) -> Option<Self> {
+		return match r {
+			Some(r) => Some(Self::QMark(Box::new(r))),
+			None => Some(Self::Empty)
+		};
+	}
+
+	pub fn repeat_opt(r:Option<Self>, min:usize, max:Option<usize>) -> Option<Self> {
+		return match r {
+			Some(r) => Some(Self::Repeat{tree:Box::new(r),min,max}),
+			None if min == 0 => Some(Self::Empty),
+			None => None
+		};
+	}
+
+	/// Builds the alternation of the given letters, or `None` if there are none.
+	pub fn from_class(letters:&Vec<Index0>) -> Option<Self> {
+		let mut result:Option<Self> = None;
+		for i in letters {
+			result = match result {
+				None => Some(Self::Single(*i)),
+				Some(r) => Some(Self::Or((Box::new(r),Box::new(Self::Single(*i)))))
+			};
+		}
+		return result;
+	}
+
+	fn letter_to_string(c:char) -> String {
+		return match Regex::SPECIAL_SYMBOLS.contains(&c) {
+			true => format!("\\{}",c),
+			false => c.to_string()
+		};
+	}
+
+	fn class_to_string(letters:&[Index0], alphabet:&[char]) -> String {
+		let mut chars:Vec<char> = letters.iter().map(|i| alphabet[i.0]).collect();
+		chars.sort();
+		let escape = |c:char| match c {
+			']' | '\\' | '^' | '-' => format!("\\{}",c),
+			_ => c.to_string()
+		};
+		let mut result = String::from("[");
+		let mut i = 0;
+		while i < chars.len() {
+			let mut j = i;
+			while j + 1 < chars.len() && chars[j+1] as u32 == chars[j] as u32 + 1 {
+				j += 1;
+			}
+			if j >= i + 2 {
+				result.push_str(&format!("{}-{}",escape(chars[i]),escape(chars[j])));
+			} else {
+				for c in &chars[i..=j] {
+					result.push_str(&escape(*c));
+				}
+			}
+			i = j + 1;
 		}
-		result.push(opp);
+		result.push(']');
 		return result;
 	}
 
-	fn concat_to_string(r1:&Self,r2:&Self,alphabet:&Vec<char>) -> String{
-		let mut s1 = match r1 {
-			Self::Or(_) => {
-				format!("({})",r1.to_string(alphabet))
+	fn repeat_opp(min:usize, max:Option<usize>) -> String {
+		return match (min,max) {
+			(0,None) => "*".to_string(),
+			(1,None) => "+".to_string(),
+			(0,Some(1)) => "?".to_string(),
+			(n,None) => format!("{{{},}}",n),
+			(n,Some(m)) if n == m => format!("{{{}}}",n),
+			(n,Some(m)) => format!("{{{},{}}}",n,m)
+		};
+	}
+
+	/// Writes the regex out, stopping if that takes longer than the limit.
+	pub fn to_string(&self, alphabet:&[char], budget:&Budget) -> Result<String,LimitError> {
+		return Shape::new(self).to_string(alphabet,budget);
+	}
+}
+
+
+/// What printing a node depends on, found for every node of the tree in one bottom-up pass, so that printing takes time linear in the size of the tree.
+struct Shape<'a> {
+	tree:&'a RegexTree,
+	/// The distinct letters, if the node is a letter or an alternation of letters.
+	letters:Option<Vec<Index0>>,
+	/// A hash of the whole subtree, so that factors can be compared without walking them.
+	key:u64,
+	children:Vec<Shape<'a>>
+}
+
+impl<'a> Shape<'a> {
+	fn new(tree:&'a RegexTree) -> Shape<'a> {
+		let children:Vec<Shape<'a>> = match tree {
+			RegexTree::Empty | RegexTree::Single(_) => Vec::new(),
+			RegexTree::KleeneStar(r) | RegexTree::KleenePlus(r) | RegexTree::QMark(r) | RegexTree::Repeat{tree:r,..} => vec![Shape::new(r)],
+			RegexTree::Concat((r1,r2)) | RegexTree::Shuffle((r1,r2)) | RegexTree::Or((r1,r2)) => vec![Shape::new(r1),Shape::new(r2)]
+		};
+		let letters = match tree {
+			RegexTree::Single(i) => Some(vec![*i]),
+			RegexTree::Or(_) => match (&children[0].letters,&children[1].letters) {
+				(Some(l1),Some(l2)) => {
+					let mut letters = l1.clone();
+					for i in l2 {
+						if !letters.contains(i) {
+							letters.push(*i);
+						}
+					}
+					Some(letters)
+				},
+				_ => None
 			},
-			_ => r1.to_string(alphabet)
+			_ => None
+		};
+		let mut hasher = DefaultHasher::new();
+		std::mem::discriminant(tree).hash(&mut hasher);
+		match tree {
+			RegexTree::Single(i) => i.hash(&mut hasher),
+			RegexTree::Repeat{min,max,..} => (min,max).hash(&mut hasher),
+			_ => ()
+		}
+		for child in &children {
+			child.key.hash(&mut hasher);
+		}
+		return Shape{tree,letters,key:hasher.finish(),children};
+	}
+
+	/// The letters, if this is an alternation of at least two distinct letters, printed as a class.
+	fn class(&self) -> Option<&Vec<Index0>> {
+		return match (self.tree,&self.letters) {
+			(RegexTree::Or(_),Some(letters)) if letters.len() >= 2 => Some(letters),
+			_ => None
+		};
+	}
+
+	fn is_atomic(&self) -> bool {
+		return match self.tree {
+			RegexTree::Concat(_) | RegexTree::Shuffle(_) => false,
+			RegexTree::Or(_) => self.class().is_some(),
+			_ => true
 		};
+	}
+
+	/// Whether the subtrees are the same, comparing them in full only when their hashes match.
+	fn same(&self, other:&Shape) -> bool {
+		return self.key == other.key && self.tree == other.tree;
+	}
 
-		let s2 = match r2 {
-			Self::Or(_) => {
-				format!("({})",r2.to_string(alphabet))
+	fn flatten_concat<'b>(&'b self, factors:&mut Vec<&'b Shape<'a>>) {
+		match self.tree {
+			RegexTree::Concat(_) => {
+				self.children[0].flatten_concat(factors);
+				self.children[1].flatten_concat(factors);
 			},
-			_ => r2.to_string(alphabet)
+			RegexTree::Empty => (),
+			_ => factors.push(self)
+		}
+	}
+
+	/// Splits a factor of a concatenation into the repeated regex and how many times it is repeated.
+	fn as_repetition(&self) -> (&Shape<'a>,usize,Option<usize>) {
+		return match self.tree {
+			RegexTree::KleeneStar(_) => (&self.children[0],0,None),
+			RegexTree::KleenePlus(_) => (&self.children[0],1,None),
+			RegexTree::QMark(_) => (&self.children[0],0,Some(1)),
+			RegexTree::Repeat{min,max,..} => (&self.children[0],*min,*max),
+			_ => (self,1,Some(1))
 		};
-		s1.push_str(&s2);
-		return s1;
 	}
-	
-	pub fn to_string(&self, alphabet:&Vec<char>) -> String {
-		return match &self {
-			Self::Empty => String::new(),
-			Self::Single(i) => alphabet[(*i).0].to_string(),
-			Self::KleeneStar(r) => Self::opp_to_string('*',&**r,alphabet),
-			Self::KleenePlus(r) => Self::opp_to_string('+',&**r,alphabet),
-			Self::QMark(r) => Self::opp_to_string('?',&**r,alphabet),
-			Self::Concat((r1,r2)) => Self::concat_to_string(&**r1,&**r2,alphabet),
-			Self::Or((r1,r2)) => format!("{}|{}",r1.to_string(alphabet),r2.to_string(alphabet)),
+
+	fn opp_to_string(opp:&str, child:&Shape, alphabet:&[char], budget:&Budget) -> Result<String,LimitError> {//regex is a mix of infix and postfix notation so brackets need to be added where appropriate
+		let child_string = match child.to_string(alphabet,budget) {
+			Ok(c) => c,
+			Err(e) => return Err(e)
+		};
+		//need brackets around ors or concats
+		return Ok(match child.is_atomic() {
+			true => format!("{}{}",child_string,opp),
+			false => format!("({}){}",child_string,opp)
+		});
+	}
+
+	/// An operand of a concatenation or shuffle, in brackets if it is an alternation.
+	fn operand_to_string(&self, alphabet:&[char], budget:&Budget) -> Result<String,LimitError> {
+		return match self.tree {
+			RegexTree::Or(_) | RegexTree::Shuffle(_) if !self.is_atomic() => self.to_string(alphabet,budget).map(|f| format!("({})",f)),
+			_ => self.to_string(alphabet,budget)
+		};
+	}
+
+	fn concat_to_string(&self, alphabet:&[char], budget:&Budget) -> Result<String,LimitError> {
+		// consecutive factors repeating the same regex, such as aaa?a?, are merged into the compact a{3,5} where that is shorter
+		let mut factors:Vec<&Shape> = Vec::new();
+		self.flatten_concat(&mut factors);
+		let mut result = String::new();
+		let mut i = 0;
+		while i < factors.len() {
+			let (base,mut min,mut max) = factors[i].as_repetition();
+			let mut long = match factors[i].operand_to_string(alphabet,budget) {
+				Ok(l) => l,
+				Err(e) => return Err(e)
+			};
+			let mut j = i + 1;
+			while j < factors.len() && factors[j].as_repetition().0.same(base) {
+				let (_,next_min,next_max) = factors[j].as_repetition();
+				min += next_min;
+				max = match (max,next_max) {
+					(Some(m1),Some(m2)) => Some(m1 + m2),
+					_ => None
+				};
+				match factors[j].operand_to_string(alphabet,budget) {
+					Ok(f) => long.push_str(&f),
+					Err(e) => return Err(e)
+				}
+				j += 1;
+			}
+			if j > i + 1 {
+				let compact = match Self::opp_to_string(&RegexTree::repeat_opp(min,max),base,alphabet,budget) {
+					Ok(c) => c,
+					Err(e) => return Err(e)
+				};
+				if compact.chars().count() < long.chars().count() {
+					long = compact;
+				}
+			}
+			result.push_str(&long);
+			i = j;
+		}
+		if result.is_empty() {
+			result.push('ε');
+		}
+		return Ok(result);
+	}
+
+	fn to_string(&self, alphabet:&[char], budget:&Budget) -> Result<String,LimitError> {
+		if let Err(e) = budget.check_time(Progress::Printing) {
+			return Err(e);
+		}
+		if let Some(letters) = self.class() {
+			return Ok(RegexTree::class_to_string(letters,alphabet));
 		}
+		let child = |i:usize| &self.children[i];
+		return match self.tree {
+			RegexTree::Empty => Ok("ε".to_string()),
+			RegexTree::Single(i) => Ok(RegexTree::letter_to_string(alphabet[i.0])),
+			RegexTree::KleeneStar(_) => Self::opp_to_string("*",child(0),alphabet,budget),
+			RegexTree::KleenePlus(_) => Self::opp_to_string("+",child(0),alphabet,budget),
+			RegexTree::QMark(_) => Self::opp_to_string("?",child(0),alphabet,budget),
+			RegexTree::Repeat{min,max,..} => Self::opp_to_string(&RegexTree::repeat_opp(*min,*max),child(0),alphabet,budget),
+			RegexTree::Concat(_) => self.concat_to_string(alphabet,budget),
+			RegexTree::Shuffle(_) => match (child(0).operand_to_string(alphabet,budget),child(1).operand_to_string(alphabet,budget)) {
+				(Ok(o1),Ok(o2)) => Ok(format!("{}%{}",o1,o2)),
+				(Err(e),_) | (_,Err(e)) => Err(e)
+			},
+			RegexTree::Or(_) => match (child(0).to_string(alphabet,budget),child(1).to_string(alphabet,budget)) {
+				(Ok(s1),Ok(s2)) => Ok(format!("{}|{}",s1,s2)),
+				(Err(e),_) | (_,Err(e)) => Err(e)
+			}
+		};
 	}
 }
 
-impl From<Vec<InProgress>> for RegexTree {
+impl RegexTree {
 
-	fn from(input:Vec<InProgress>) -> Self {
-		if input.len() == 0 {
-			return Self::Empty;
+	fn from_tokens(input:Vec<InProgress>) -> Option<Self> {
+		if input.is_empty() {
+			return Some(Self::Empty);
 		}
 		if input.len() == 1 {
 			return match &input[0] {
 				InProgress::Reg(r) => r.clone(),
-				_ => Self::Empty//due to earlier checks, we know brackets match, so do not need to consider them here, and a single unary operator, or | on its own is equivalent to empty
+				_ => Some(Self::Empty)//due to earlier checks, we know brackets match, so do not need to consider them here, and a single unary operator, or | on its own is equivalent to empty
 			};
 		}
 		let mut input = input.clone();
@@ -316,14 +726,15 @@ impl From<Vec<InProgress>> for RegexTree {
 		Self::process_brackets(&mut input);
 		//unary operators are next
 		Self::process_unary(&mut input);
-		//now, all we're left with is InProgress::Regs and InProgress::Ors
+		//now, all we're left with is InProgress::Regs, InProgress::Shuffles and InProgress::Ors
 		Self::process_concat(&mut input);
+		Self::process_shuffle(&mut input);
 		//now just to deal with the Ors
 		Self::process_or(&mut input);
 		return if let InProgress::Reg(r) = &input[0] {
 			r.clone()
 		} else {
-			Self::Empty //can't be reached due to earlier code
+			Some(Self::Empty) //can't be reached due to earlier code
 		}
 	}
 
@@ -331,25 +742,30 @@ impl From<Vec<InProgress>> for RegexTree {
 
 #[derive(Clone,Debug)]
 enum InProgress {
-    Reg(RegexTree),
+    Reg(Option<RegexTree>), // None is the empty language
     KStar,
     KPlus,
     QMark,
+    Repeat(usize,Option<usize>),
+    Shuffle,
     Or,
     Open,
     Close
 }
 impl InProgress {
-	fn from_char(c:char, hm:&HashMap<char,Index0>) -> InProgress {
-		match c {
-			'*' => InProgress::KStar,
-			'+' => InProgress::KPlus,
-			'?' => InProgress::QMark,
-			'|' => InProgress::Or,
-			'(' => InProgress::Open,
-			')' => InProgress::Close,
-			other => InProgress::Reg(RegexTree::Single(hm[&other]))
-		}    
+	fn from_lexeme(lexeme:&Lexeme, alphabet:&str, hm:&HashMap<char,Index0>) -> InProgress {
+		return match lexeme {
+			Lexeme::Operator('*') => InProgress::KStar,
+			Lexeme::Operator('+') => InProgress::KPlus,
+			Lexeme::Operator('?') => InProgress::QMark,
+			Lexeme::Operator('|') => InProgress::Or,
+			Lexeme::Operator('%') => InProgress::Shuffle,
+			Lexeme::Operator('(') => InProgress::Open,
+			Lexeme::Operator(_) => InProgress::Close,
+			Lexeme::Repeat(min,max) => InProgress::Repeat(*min,*max),
+			Lexeme::Epsilon => InProgress::Reg(Some(RegexTree::Empty)),
+			Lexeme::EmptySet => InProgress::Reg(None),
+			other => InProgress::Reg(regex_parser::letters_to_tree(other,alphabet,hm))
+		};
 	}
-	
 }
@@ -0,0 +1,475 @@
+use std::fmt;
+use std::collections::HashMap;
+
+use crate::regex::RegexTree;
+
+use crate::Index0;
+
+/// The columns of the regex (indexed from 1) that a token or subexpression was read from, end exclusive.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Span {
+	pub start:usize,
+	pub end:usize
+}
+
+impl Span {
+	fn new(start:usize,end:usize) -> Self {
+		Self{start,end}
+	}
+	fn join(&self,other:&Self) -> Self {
+		Self::new(self.start.min(other.start),self.end.max(other.end))
+	}
+}
+
+impl fmt::Display for Span {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f,"column {}",self.start)
+	}
+}
+
+/// The regex after escapes and character classes have been resolved, but before the alphabet is known.
+#[derive(Clone,Debug)]
+pub enum Lexeme {
+	Letter(char),
+	Operator(char),
+	Any,
+	Epsilon,
+	EmptySet,
+	Class(bool,Vec<char>), //whether the class is negated, and the letters it lists
+	Repeat(usize,Option<usize>)
+}
+
+#[derive(Clone,Debug)]
+pub struct Token {
+	pub lexeme:Lexeme,
+	pub span:Span
+}
+
+fn lex_class_letter(regex:&[char], i:&mut usize) -> Result<char,String> {
+	if regex[*i] == '\\' {
+		*i += 1;
+		if *i == regex.len() {
+			return Err(format!("Unfinished escape at column {}",*i));
+		}
+	}
+	let c = regex[*i];
+	*i += 1;
+	return Ok(c);
+}
+
+fn lex_class(regex:&[char], i:&mut usize) -> Result<Lexeme,String> {
+	// i points just after the opening '['
+	let open = *i;
+	let mut negated = false;
+	if *i < regex.len() && regex[*i] == '^' {
+		negated = true;
+		*i += 1;
+	}
+	let mut letters:Vec<char> = Vec::new();
+	loop {
+		if *i == regex.len() {
+			return Err(format!("Unclosed character class at column {}",open));
+		}
+		if regex[*i] == ']' {
+			*i += 1;
+			break;
+		}
+		let first_column = *i + 1;
+		let first = match lex_class_letter(regex, i) {
+			Err(e) => return Err(e),
+			Ok(c) => c
+		};
+		if *i + 1 < regex.len() && regex[*i] == '-' && regex[*i+1] != ']' {
+			*i += 1;
+			let last = match lex_class_letter(regex, i) {
+				Err(e) => return Err(e),
+				Ok(c) => c
+			};
+			if last < first {
+				return Err(format!("The range {}-{} at column {} is in the wrong order",first,last,first_column));
+			}
+			for c in first..=last {
+				if !letters.contains(&c) {
+					letters.push(c);
+				}
+			}
+		} else if !letters.contains(&first) {
+			letters.push(first);
+		}
+	}
+	if letters.is_empty() && !negated {
+		return Err(format!("Empty character class at column {}",open));
+	}
+	return Ok(Lexeme::Class(negated,letters));
+}
+
+fn lex_number(regex:&[char], i:&mut usize) -> Option<usize> {
+	let start = *i;
+	while *i < regex.len() && regex[*i].is_ascii_digit() {
+		*i += 1;
+	}
+	return regex[start..*i].iter().collect::<String>().parse().ok();
+}
+
+fn lex_repeat(regex:&[char], i:&mut usize) -> Result<Lexeme,String> {
+	// i points just after the opening '{', which is at column i
+	let column = *i;
+	let malformed = format!("Bounded repetition at column {} must be of the form {{n}}, {{n,}} or {{n,m}}",*i);
+	let min = match lex_number(regex, i) {
+		None => return Err(malformed),
+		Some(n) => n
+	};
+	if *i == regex.len() {
+		return Err(malformed);
+	}
+	let max = match regex[*i] {
+		'}' => Some(min),
+		',' => {
+			*i += 1;
+			let max = lex_number(regex, i);
+			if *i == regex.len() || regex[*i] != '}' {
+				return Err(malformed);
+			}
+			max
+		},
+		_ => return Err(malformed)
+	};
+	*i += 1;
+	if let Some(max) = max {
+		if max < min {
+			return Err(format!("The bounded repetition {{{},{}}} at column {} has a maximum smaller than its minimum",min,max,column));
+		}
+	}
+	return Ok(Lexeme::Repeat(min,max));
+}
+
+pub fn lex(regex:&[char]) -> Result<Vec<Token>,String> {
+	let mut result:Vec<Token> = Vec::new();
+	let mut i = 0;
+	while i < regex.len() {
+		let start = i;
+		let c = regex[i];
+		i += 1;
+		let lexeme = match c {
+			'(' | ')' | '|' | '%' | '+' | '?' | '*' => Lexeme::Operator(c),
+			'.' => Lexeme::Any,
+			'ε' => Lexeme::Epsilon,
+			'∅' => Lexeme::EmptySet,
+			'[' => match lex_class(regex, &mut i) {
+				Err(e) => return Err(e),
+				Ok(class) => class
+			},
+			']' => return Err(format!("Unmatched ']' at column {}",i)),
+			'{' => match lex_repeat(regex, &mut i) {
+				Err(e) => return Err(e),
+				Ok(repeat) => repeat
+			},
+			'}' => return Err(format!("Unmatched '}}' at column {}",i)),
+			'\\' => {
+				if i == regex.len() {
+					return Err(format!("Unfinished escape at column {}",i));
+				}
+				i += 1;
+				match regex[i-1] {
+					'0' => Lexeme::EmptySet,
+					other => Lexeme::Letter(other)
+				}
+			},
+			other => Lexeme::Letter(other)
+		};
+		result.push(Token{lexeme,span:Span::new(start+1,i+1)});
+	}
+	return Ok(result);
+}
+
+/// Finds the alphabet of a regex, checking the letters against the declared alphabet if there is one.
+pub fn get_regex_alphabet(regex:&Vec<Token>, declared:Option<&str>) -> Result<String,String> {
+	let mut alphabet:Vec<char> = Vec::new();
+	for token in regex {
+		let letters:Vec<char> = match &token.lexeme {
+			Lexeme::Letter(c) => vec![*c],
+			Lexeme::Class(_,letters) => letters.clone(),
+			_ => Vec::new()
+		};
+		for c in letters {
+			if alphabet.contains(&c) {
+				continue;
+			}
+			if let Some(declared) = declared {
+				if !declared.contains(c) {
+					if let Lexeme::Class(true,_) = token.lexeme {
+						continue; //excluding a letter that is not in the alphabet anyway is harmless
+					}
+					return Err(format!("The letter {} at {} is not in the declared alphabet",c,token.span));
+				}
+			}
+			alphabet.push(c);
+		}
+	}
+	return match declared {
+		Some(declared) => Ok(declared.to_string()),
+		None => Ok(alphabet.iter().cloned().collect())
+	};
+}
+
+/// Resolves the letters matched by a wildcard or character class into a tree over the alphabet.
+pub fn letters_to_tree(lexeme:&Lexeme, alphabet:&str, hm:&HashMap<char,Index0>) -> Option<RegexTree> {
+	let letters:Vec<char> = match lexeme {
+		Lexeme::Letter(c) => vec![*c],
+		Lexeme::Any => alphabet.chars().collect(),
+		Lexeme::Class(false,letters) => letters.clone(),
+		Lexeme::Class(true,letters) => alphabet.chars().filter(|c| !letters.contains(c)).collect(),
+		_ => Vec::new()
+	};
+	let mut letters:Vec<Index0> = letters.iter().map(|c| hm[c]).collect();
+	letters.sort_by_key(|i| i.0);
+	return RegexTree::from_class(&letters);
+}
+
+#[derive(Clone,Debug)]
+pub enum AstKind {
+	Leaf(Lexeme), // a letter, wildcard, class, ε or ∅
+	Postfix(Box<Ast>,Lexeme), // *, +, ? or a bounded repetition
+	Concat(Vec<Ast>),
+	Shuffle(Vec<Ast>),
+	Or(Vec<Ast>)
+}
+
+/// A parsed regex, keeping track of where each part of it was written.
+#[derive(Clone,Debug)]
+pub struct Ast {
+	pub kind:AstKind,
+	pub span:Span
+}
+
+impl Ast {
+	pub fn to_tree(&self, alphabet:&str, hm:&HashMap<char,Index0>) -> Option<RegexTree> {
+		return match &self.kind {
+			AstKind::Leaf(Lexeme::Epsilon) => Some(RegexTree::Empty),
+			AstKind::Leaf(Lexeme::EmptySet) => None,
+			AstKind::Leaf(l) => letters_to_tree(l,alphabet,hm),
+			AstKind::Postfix(r,op) => {
+				let r = r.to_tree(alphabet,hm);
+				match op {
+					Lexeme::Operator('*') => RegexTree::star_opt(r),
+					Lexeme::Operator('+') => RegexTree::plus_opt(r),
+					Lexeme::Operator('?') => RegexTree::qmark_opt(r),
+					Lexeme::Repeat(min,max) => RegexTree::repeat_opt(r,*min,*max),
+					_ => unreachable!("only postfix operators are stored as postfix nodes")
+				}
+			},
+			AstKind::Concat(parts) => parts.iter()
+				.map(|p| p.to_tree(alphabet,hm))
+				.reduce(RegexTree::concat_opt)
+				.unwrap_or(Some(RegexTree::Empty)),
+			AstKind::Shuffle(parts) => parts.iter()
+				.map(|p| p.to_tree(alphabet,hm))
+				.reduce(RegexTree::shuffle_opt)
+				.unwrap_or(Some(RegexTree::Empty)),
+			AstKind::Or(parts) => parts.iter()
+				.map(|p| p.to_tree(alphabet,hm))
+				.reduce(RegexTree::or_opt)
+				.unwrap_or(None)
+		};
+	}
+}
+
+/// Recursive descent parser for the grammar
+///
+/// alt     := shuffle ('|' shuffle)*
+/// shuffle := concat ('%' concat)*
+/// concat  := postfix postfix*
+/// postfix := atom ('*' | '+' | '?' | '{n}' | '{n,}' | '{n,m}')*
+/// atom    := letter | '.' | class | 'ε' | '∅' | '(' alt? ')'
+///
+/// An empty regex is the empty word.
+pub struct Parser<'a> {
+	tokens:&'a Vec<Token>,
+	position:usize
+}
+
+impl<'a> Parser<'a> {
+	pub fn parse(tokens:&'a Vec<Token>) -> Result<Ast,String> {
+		let mut parser = Parser{tokens,position:0};
+		let ast = match parser.parse_alt() {
+			Err(e) => return Err(e),
+			Ok(a) => a
+		};
+		if let Some(token) = parser.peek() {
+			return Err(format!("Unmatched ')' at {}",token.span));//the only token an alternation stops at
+		}
+		return Ok(match ast {
+			Some(a) => a,
+			None => Ast{kind:AstKind::Leaf(Lexeme::Epsilon),span:Span::new(1,1)}
+		});
+	}
+
+	fn peek(&self) -> Option<&'a Token> {
+		return self.tokens.get(self.position);
+	}
+
+	fn peek_operator(&self) -> Option<char> {
+		return match self.peek() {
+			Some(Token{lexeme:Lexeme::Operator(c),..}) => Some(*c),
+			_ => None
+		};
+	}
+
+	fn is_postfix(token:&Token) -> bool {
+		return matches!(token.lexeme, Lexeme::Operator('*') | Lexeme::Operator('+') | Lexeme::Operator('?') | Lexeme::Repeat(_,_));
+	}
+
+	fn parse_alt(&mut self) -> Result<Option<Ast>,String> {
+		return self.parse_infix('|',Self::parse_shuffle,AstKind::Or);
+	}
+
+	fn parse_shuffle(&mut self) -> Result<Option<Ast>,String> {
+		return self.parse_infix('%',Self::parse_concat,AstKind::Shuffle);
+	}
+
+	/// Parses operands separated by an infix operator, each of which must be present.
+	fn parse_infix(&mut self, operator:char, parse_operand:fn(&mut Self) -> Result<Option<Ast>,String>, kind:fn(Vec<Ast>) -> AstKind) -> Result<Option<Ast>,String> {
+		let first = match parse_operand(self) {
+			Err(e) => return Err(e),
+			Ok(c) => c
+		};
+		if self.peek_operator() != Some(operator) {
+			return Ok(first);
+		}
+		let mut parts:Vec<Ast> = Vec::new();
+		let mut last = first;
+		while self.peek_operator() == Some(operator) {
+			let bar = self.peek().unwrap().span;
+			self.position += 1;
+			match last {
+				None => return Err(format!("Dangling operator '{}' at {}, it has nothing on its left",operator,bar)),
+				Some(a) => parts.push(a)
+			}
+			last = match parse_operand(self) {
+				Err(e) => return Err(e),
+				Ok(None) => return Err(match self.peek() {
+					// the operand stopped at a token that cannot start one
+					Some(Token{lexeme:Lexeme::Operator(c),span}) => format!("Unexpected '{}' at {}, the operator '{}' at {} needs something on its right",c,span,operator,bar),
+					_ => format!("Dangling operator '{}' at {}, it has nothing on its right",operator,bar)
+				}),
+				Ok(c) => c
+			};
+		}
+		parts.push(last.unwrap());
+		let span = parts[0].span.join(&parts[parts.len()-1].span);
+		return Ok(Some(Ast{kind:kind(parts),span}));
+	}
+
+	fn parse_concat(&mut self) -> Result<Option<Ast>,String> {
+		let mut parts:Vec<Ast> = Vec::new();
+		while let Some(token) = self.peek() {
+			if let Lexeme::Operator('|') | Lexeme::Operator('%') | Lexeme::Operator(')') = token.lexeme {
+				break;
+			}
+			parts.push(match self.parse_postfix() {
+				Err(e) => return Err(e),
+				Ok(p) => p
+			});
+		}
+		return Ok(match parts.len() {
+			0 => None,
+			1 => parts.pop(),
+			_ => {
+				let span = parts[0].span.join(&parts[parts.len()-1].span);
+				Some(Ast{kind:AstKind::Concat(parts),span})
+			}
+		});
+	}
+
+	fn parse_postfix(&mut self) -> Result<Ast,String> {
+		let mut ast = match self.parse_atom() {
+			Err(e) => return Err(e),
+			Ok(a) => a
+		};
+		while let Some(token) = self.peek() {
+			if !Self::is_postfix(token) {
+				break;
+			}
+			self.position += 1;
+			let span = ast.span.join(&token.span);
+			ast = Ast{kind:AstKind::Postfix(Box::new(ast),token.lexeme.clone()),span};
+		}
+		return Ok(ast);
+	}
+
+	fn parse_atom(&mut self) -> Result<Ast,String> {
+		let token = self.peek().expect("parse_concat only calls this when there is a token");
+		if Self::is_postfix(token) {
+			let operator = match &token.lexeme {
+				Lexeme::Operator(c) => c.to_string(),
+				_ => "{}".to_string()
+			};
+			return Err(format!("Dangling operator '{}' at {}, it has nothing to apply to",operator,token.span));
+		}
+		self.position += 1;
+		if let Lexeme::Operator('(') = token.lexeme {
+			let inner = match self.parse_alt() {
+				Err(e) => return Err(e),
+				Ok(i) => i
+			};
+			let close = match self.peek() {
+				Some(t) if matches!(t.lexeme, Lexeme::Operator(')')) => t,
+				_ => return Err(format!("Unclosed '(' at {}",token.span))
+			};
+			self.position += 1;
+			let span = token.span.join(&close.span);
+			return Ok(match inner {
+				None => Ast{kind:AstKind::Leaf(Lexeme::Epsilon),span},
+				Some(mut a) => {
+					a.span = span;
+					a
+				}
+			});
+		}
+		return Ok(Ast{kind:AstKind::Leaf(token.lexeme.clone()),span:token.span});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn error(regex:&str) -> String {
+		let chars:Vec<char> = regex.chars().collect();
+		let tokens = match lex(&chars) {
+			Err(e) => return e,
+			Ok(t) => t
+		};
+		return Parser::parse(&tokens).err().unwrap_or_else(|| panic!("{} should not parse",regex));
+	}
+
+	#[test]
+	fn reversed_bounds() {
+		assert_eq!(error("a{3,1}"),"The bounded repetition {3,1} at column 2 has a maximum smaller than its minimum");
+		assert_eq!(error("a{2}{1,0}"),"The bounded repetition {1,0} at column 5 has a maximum smaller than its minimum");
+		assert_eq!(error("[b-a]"),"The range b-a at column 2 is in the wrong order");
+	}
+
+	#[test]
+	fn dangling_operators() {
+		assert_eq!(error("*a"),"Dangling operator '*' at column 1, it has nothing to apply to");
+		assert_eq!(error("a|*"),"Dangling operator '*' at column 3, it has nothing to apply to");
+		assert_eq!(error("(|a)"),"Dangling operator '|' at column 2, it has nothing on its left");
+		assert_eq!(error("a|"),"Dangling operator '|' at column 2, it has nothing on its right");
+		assert_eq!(error("a||b"),"Unexpected '|' at column 3, the operator '|' at column 2 needs something on its right");
+	}
+
+	#[test]
+	fn unclosed_groups() {
+		assert_eq!(error("(ab"),"Unclosed '(' at column 1");
+		assert_eq!(error("a(b|(c)"),"Unclosed '(' at column 2");
+		assert_eq!(error("a)"),"Unmatched ')' at column 2");
+		assert_eq!(error("[a"),"Unclosed character class at column 1");
+	}
+
+	#[test]
+	fn bad_escapes() {
+		assert_eq!(error("ab\\"),"Unfinished escape at column 3");
+		assert_eq!(error("[a\\"),"Unfinished escape at column 3");
+	}
+}
@@ -0,0 +1,421 @@
+use crate::regex::Regex;
+use crate::regex::RegexTree;
+use crate::limits::Budget;
+use crate::limits::LimitError;
+use crate::limits::Progress;
+
+/*
+Rewrites a regex into an equivalent, usually much shorter, one. Each pass rewrites the tree bottom up, and passes are repeated until nothing changes or the time limit is reached. The rewrites used are:
+ - ε is removed from concatenations, and εr, rε become r.
+ - Nested and redundant postfix operators are merged, e.g. (r*)* -> r*, (r+)? -> r*, r?* -> r*, and r? -> r if r accepts the empty word.
+ - Repeated alternatives are removed, r|r -> r, and alternatives absorbed by a star are removed, e.g. r|r* -> r*.
+ - ε|r -> r?, and so ε|rr* -> ε|r+ -> r*.
+ - Neighbouring factors of a concatenation repeating the same regex are merged, e.g. rr* -> r+, r?r* -> r*, ab(ab)* -> (ab)+.
+ - Common prefixes and suffixes of alternatives are factored out, e.g. ab|ac -> a(b|c).
+*/
+
+impl Regex {
+	pub fn simplify(&self, budget:&Budget) -> Result<Regex,LimitError> {
+		return match &self.tree {
+			None => Ok(self.clone()),
+			Some(t) => t.simplify(budget).map(|t| Regex::new(self.alphabet.clone(),Some(t)))
+		};
+	}
+}
+
+impl RegexTree {
+	/// Repeats passes until nothing changes, stopping if that takes longer than the limit.
+	pub fn simplify(&self, budget:&Budget) -> Result<RegexTree,LimitError> {
+		let mut current = self.clone();
+		let mut passes = 0;
+		loop {
+			let next = match current.simplify_step(budget,Progress::Simplifying{passes}) {
+				Ok(n) => n,
+				Err(e) => return Err(e)
+			};
+			if next == current {
+				return Ok(current);
+			}
+			current = next;
+			passes += 1;
+		}
+	}
+
+	fn simplify_step(&self, budget:&Budget, progress:Progress) -> Result<RegexTree,LimitError> {
+		if let Err(e) = budget.check_time(progress) {
+			return Err(e);
+		}
+		return match self {
+			Self::Empty | Self::Single(_) => Ok(self.clone()),
+			Self::KleeneStar(r) => r.simplify_step(budget,progress).map(simplify_star),
+			Self::KleenePlus(r) => r.simplify_step(budget,progress).map(simplify_plus),
+			Self::QMark(r) => r.simplify_step(budget,progress).map(simplify_qmark),
+			Self::Repeat{tree,min,max} => tree.simplify_step(budget,progress).map(|t| simplify_repeat(t,*min,*max)),
+			Self::Concat(_) => {
+				let mut factors:Vec<RegexTree> = Vec::new();
+				self.flatten_concat_owned(&mut factors);
+				simplify_each(&factors,budget,progress).map(simplify_concat)
+			},
+			Self::Shuffle((r1,r2)) => match (r1.simplify_step(budget,progress),r2.simplify_step(budget,progress)) {
+				(Ok(s1),Ok(s2)) => Ok(simplify_shuffle(s1,s2)),
+				(Err(e),_) | (_,Err(e)) => Err(e)
+			},
+			Self::Or(_) => {
+				let mut alternatives:Vec<RegexTree> = Vec::new();
+				self.flatten_or(&mut alternatives);
+				simplify_each(&alternatives,budget,progress).map(simplify_or)
+			}
+		};
+	}
+
+	fn flatten_concat_owned(&self, factors:&mut Vec<RegexTree>) {
+		match self {
+			Self::Concat((r1,r2)) => {
+				r1.flatten_concat_owned(factors);
+				r2.flatten_concat_owned(factors);
+			},
+			other => factors.push(other.clone())
+		}
+	}
+
+	fn flatten_or(&self, alternatives:&mut Vec<RegexTree>) {
+		match self {
+			Self::Or((r1,r2)) => {
+				r1.flatten_or(alternatives);
+				r2.flatten_or(alternatives);
+			},
+			other => alternatives.push(other.clone())
+		}
+	}
+}
+
+fn simplify_each(trees:&[RegexTree], budget:&Budget, progress:Progress) -> Result<Vec<RegexTree>,LimitError> {
+	let mut result:Vec<RegexTree> = Vec::new();
+	for t in trees {
+		match t.simplify_step(budget,progress) {
+			Ok(s) => result.push(s),
+			Err(e) => return Err(e)
+		}
+	}
+	return Ok(result);
+}
+
+fn boxed(r:&RegexTree) -> Box<RegexTree> {
+	return Box::new(r.clone());
+}
+
+/// Removes the outermost postfix operator of a regex inside a star, as it makes no difference there.
+fn strip_for_star(r:RegexTree) -> RegexTree {
+	return match r {
+		RegexTree::KleeneStar(x) | RegexTree::KleenePlus(x) | RegexTree::QMark(x) => *x,
+		RegexTree::Repeat{tree,min,max} if min <= 1 && max != Some(0) => *tree,
+		other => other
+	};
+}
+
+fn simplify_star(r:RegexTree) -> RegexTree {
+	let r = strip_for_star(r);
+	if let RegexTree::Or(_) = r {
+		// (ε|a*|b)* -> (a|b)*
+		let mut alternatives:Vec<RegexTree> = Vec::new();
+		r.flatten_or(&mut alternatives);
+		let alternatives:Vec<RegexTree> = alternatives.into_iter()
+			.filter(|a| *a != RegexTree::Empty)
+			.map(strip_for_star)
+			.collect();
+		return match build_or(alternatives) {
+			RegexTree::Empty => RegexTree::Empty,
+			inner => RegexTree::KleeneStar(Box::new(inner))
+		};
+	}
+	return match r {
+		RegexTree::Empty => RegexTree::Empty,
+		other => RegexTree::KleeneStar(Box::new(other))
+	};
+}
+
+fn simplify_plus(r:RegexTree) -> RegexTree {
+	return match r {
+		RegexTree::Empty => RegexTree::Empty,
+		RegexTree::KleeneStar(_) | RegexTree::KleenePlus(_) => r,
+		RegexTree::QMark(x) => simplify_star(*x),
+		other if other.nullable() => simplify_star(other),
+		other => RegexTree::KleenePlus(Box::new(other))
+	};
+}
+
+fn simplify_qmark(r:RegexTree) -> RegexTree {
+	return match r {
+		RegexTree::KleenePlus(x) => simplify_star(*x),
+		other if other.nullable() => other,
+		other => RegexTree::QMark(Box::new(other))
+	};
+}
+
+fn simplify_repeat(r:RegexTree, min:usize, max:Option<usize>) -> RegexTree {
+	if r == RegexTree::Empty {
+		return RegexTree::Empty;
+	}
+	return match (min,max) {
+		(_,Some(0)) => RegexTree::Empty,
+		(1,Some(1)) => r,
+		(0,None) => simplify_star(r),
+		(1,None) => simplify_plus(r),
+		(0,Some(1)) => simplify_qmark(r),
+		_ => RegexTree::Repeat{tree:Box::new(r),min,max}
+	};
+}
+
+fn simplify_shuffle(r1:RegexTree, r2:RegexTree) -> RegexTree {
+	return match (r1,r2) {
+		(RegexTree::Empty,r) | (r,RegexTree::Empty) => r,
+		(r1,r2) => RegexTree::Shuffle((Box::new(r1),Box::new(r2)))
+	};
+}
+
+/// Merges two neighbouring factors of a concatenation into one, if they repeat the same regex in a way that can be written as a single factor.
+fn merge_factors(f1:&RegexTree, f2:&RegexTree) -> Option<RegexTree> {
+	use RegexTree::*;
+	return match (f1,f2) {
+		(KleeneStar(x),KleeneStar(y)) | (KleeneStar(x),QMark(y)) | (QMark(x),KleeneStar(y)) if x == y => Some(KleeneStar(boxed(x))),
+		(KleeneStar(x),KleenePlus(y)) | (KleenePlus(x),KleeneStar(y)) if x == y => Some(KleenePlus(boxed(x))),
+		(KleeneStar(x),y) | (y,KleeneStar(x)) if **x == *y => Some(KleenePlus(boxed(x))),
+		(KleenePlus(x),QMark(y)) | (QMark(x),KleenePlus(y)) if x == y => Some(KleenePlus(boxed(x))),
+		_ => None
+	};
+}
+
+fn build_concat(factors:Vec<RegexTree>) -> RegexTree {
+	let mut factors:Vec<RegexTree> = factors.into_iter().filter(|f| *f != RegexTree::Empty).collect();
+	let mut result = match factors.pop() {
+		None => return RegexTree::Empty,
+		Some(f) => f
+	};
+	while let Some(f) = factors.pop() {
+		result = RegexTree::Concat((Box::new(f),Box::new(result)));
+	}
+	return result;
+}
+
+fn build_or(alternatives:Vec<RegexTree>) -> RegexTree {
+	let mut alternatives = alternatives;
+	let mut result = match alternatives.pop() {
+		None => return RegexTree::Empty,
+		Some(a) => a
+	};
+	while let Some(a) = alternatives.pop() {
+		result = RegexTree::Or((Box::new(a),Box::new(result)));
+	}
+	return result;
+}
+
+fn simplify_concat(factors:Vec<RegexTree>) -> RegexTree {
+	let mut result:Vec<RegexTree> = Vec::new();
+	for factor in factors {
+		if factor == RegexTree::Empty {
+			continue;
+		}
+		let mut inner:Vec<RegexTree> = Vec::new();
+		factor.flatten_concat_owned(&mut inner);
+		for f in inner {
+			if let RegexTree::KleeneStar(x) = &f {
+				// r1r2(r1r2)* -> (r1r2)+
+				let body = factors_of(x);
+				if body.len() > 1 && result.ends_with(&body) {
+					result.truncate(result.len()-body.len());
+					result.push(RegexTree::KleenePlus(x.clone()));
+					continue;
+				}
+			}
+			match result.last().and_then(|last| merge_factors(last,&f)) {
+				Some(merged) => {
+					result.pop();
+					result.push(merged);
+				},
+				None => result.push(f)
+			}
+		}
+	}
+	return build_concat(result);
+}
+
+fn factors_of(r:&RegexTree) -> Vec<RegexTree> {
+	let mut factors:Vec<RegexTree> = Vec::new();
+	r.flatten_concat_owned(&mut factors);
+	return factors.into_iter().filter(|f| *f != RegexTree::Empty).collect();
+}
+
+/// Factors out the first (or last) factor shared by several alternatives, returning None if no two alternatives share one.
+fn factor_alternatives(alternatives:&[RegexTree], from_front:bool) -> Option<Vec<RegexTree>> {
+	let split:Vec<Vec<RegexTree>> = alternatives.iter().map(factors_of).collect();
+	let end = |f:&Vec<RegexTree>| -> Option<RegexTree> {
+		return match from_front {
+			true => f.first().cloned(),
+			false => f.last().cloned()
+		};
+	};
+	for i in 0..split.len() {
+		let shared = match end(&split[i]) {
+			None => continue,
+			Some(s) => s
+		};
+		let sharing:Vec<usize> = (i..split.len()).filter(|j| end(&split[*j]).as_ref() == Some(&shared)).collect();
+		if sharing.len() < 2 {
+			continue;
+		}
+		let rests:Vec<RegexTree> = sharing.iter().map(|j| {
+			let f = &split[*j];
+			match from_front {
+				true => build_concat(f[1..].to_vec()),
+				false => build_concat(f[..f.len()-1].to_vec())
+			}
+		}).collect();
+		let rest = simplify_or(rests);
+		let factored = match from_front {
+			true => build_concat(vec![shared,rest]),
+			false => build_concat(vec![rest,shared])
+		};
+		let mut result:Vec<RegexTree> = Vec::new();
+		for (j,alternative) in alternatives.iter().enumerate() {
+			if j == i {
+				result.push(factored.clone());
+			} else if !sharing.contains(&j) {
+				result.push(alternative.clone());
+			}
+		}
+		return Some(result);
+	}
+	return None;
+}
+
+/// Whether an alternative adds nothing to the language of another alternative.
+fn absorbed_by(r:&RegexTree, other:&RegexTree) -> bool {
+	use RegexTree::*;
+	return match other {
+		KleeneStar(x) => *r == Empty || **x == *r || *r == KleenePlus(x.clone()) || *r == QMark(x.clone()),
+		KleenePlus(x) => **x == *r,
+		QMark(x) => *r == Empty || **x == *r,
+		_ => false
+	};
+}
+
+fn simplify_or(alternatives:Vec<RegexTree>) -> RegexTree {
+	let mut flat:Vec<RegexTree> = Vec::new();
+	for a in alternatives {
+		let mut inner:Vec<RegexTree> = Vec::new();
+		a.flatten_or(&mut inner);
+		for a in inner {
+			if !flat.contains(&a) {
+				flat.push(a);
+			}
+		}
+	}
+	let mut kept:Vec<RegexTree> = Vec::new();
+	for i in 0..flat.len() {
+		let absorbed = (0..flat.len()).any(|j| j != i && absorbed_by(&flat[i],&flat[j]));
+		if !absorbed {
+			kept.push(flat[i].clone());
+		}
+	}
+	let has_empty = kept.contains(&RegexTree::Empty);
+	let mut kept:Vec<RegexTree> = kept.into_iter().filter(|a| *a != RegexTree::Empty).collect();
+	if let Some(factored) = factor_alternatives(&kept,true) {
+		kept = factored;
+	} else if let Some(factored) = factor_alternatives(&kept,false) {
+		kept = factored;
+	}
+	if kept.is_empty() {
+		return RegexTree::Empty;
+	}
+	let nullable = kept.iter().any(|a| a.nullable());
+	let result = build_or(kept);
+	return match has_empty && !nullable {
+		true => simplify_qmark(result),
+		false => result
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dfa::DFA;
+	use crate::limits::Limits;
+	use crate::nfa::NFA;
+
+	fn parse(regex:&str) -> Regex {
+		return Regex::parse(regex,Some("abc"),false).unwrap();
+	}
+
+	fn simplified(regex:&Regex) -> Regex {
+		return regex.simplify(&Limits::default().start()).unwrap();
+	}
+
+	/// A regex before and after simplifying, one or two for each rewrite.
+	const REWRITES:[(&str,&str);29] = [
+		("εa","a"),
+		("aε","a"),
+		("(a*)*","a*"),
+		("(a+)?","a*"),
+		("(a?)*","a*"),
+		("(a*)?","a*"),
+		("(a+)*","a*"),
+		("(a?)+","a*"),
+		("(a*)+","a*"),
+		("(a*b*)?","a*b*"),
+		("a|a","a"),
+		("a|b|a","[ab]"),
+		("a|a*","a*"),
+		("a+|a*","a*"),
+		("a*|a+|a?","a*"),
+		("ε|a","a?"),
+		("ε|aa*","a*"),
+		("(ε|a*|b)*","[ab]*"),
+		("aa*","a+"),
+		("a?a*","a*"),
+		("a*a+","a+"),
+		("ab(ab)*","(ab)+"),
+		("ab|ac","a[bc]"),
+		("ac|bc","[ab]c"),
+		("a{0}","ε"),
+		("a{1}","a"),
+		("a{0,1}","a?"),
+		("a{1,}","a+"),
+		("ε%a","a")
+	];
+
+	const LARGER:[&str;6] = [
+		"(a|b)*(a|b)+|ε",
+		"((ab)*|(ab)+)?c(a|b|c)*",
+		"(a?b?c?)*(abc|abb|ab)",
+		"a(b|c)*a|a(b|c)*b|ε|a*",
+		"(a{2,3}|b{0,2})*c?c*",
+		"(ab|a)%(c|ε)"
+	];
+
+	#[test]
+	fn each_rewrite() {
+		for (regex,expected) in REWRITES {
+			assert_eq!(simplified(&parse(regex)).to_string(),expected,"{}",regex);
+		}
+	}
+
+	#[test]
+	fn keeps_the_language() {
+		for regex in REWRITES.iter().map(|(r,_)| *r).chain(LARGER) {
+			let regex = parse(regex);
+			let before = DFA::from(&NFA::from(&regex));
+			let after = DFA::from(&NFA::from(&simplified(&regex)));
+			assert_eq!(before.distinguishing_word(&after),None,"{}",regex);
+		}
+	}
+
+	#[test]
+	fn reaches_a_fixpoint() {
+		let budget = Limits::default().start();
+		for regex in REWRITES.iter().map(|(r,_)| *r).chain(LARGER) {
+			let once = simplified(&parse(regex)).tree.unwrap();
+			assert_eq!(once.simplify_step(&budget,Progress::Simplifying{passes:0}).unwrap(),once,"{}",regex);
+			assert_eq!(once.simplify(&budget).unwrap(),once,"{}",regex);
+		}
+	}
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::StateNum;
+use crate::dfa::DFA;
+use crate::nfa::NFA;
+
+/*
+Drawings of automata in the DOT language of Graphviz, e.g. rendered with `dot -Tsvg`. The states are numbered from 1 as in .dfa and .nfa files, and the letters of parallel edges are joined into one label.
+*/
+
+impl DFA {
+	/// The DFA in the DOT language. Transitions to the implicit sink state are not drawn.
+	pub fn to_dot(&self) -> String {
+		let letters:Vec<char> = self.alphabet.chars().collect();
+		let mut edges:Vec<(StateNum,StateNum,String)> = Vec::new();
+		for (from,s) in self.states.iter().enumerate() {
+			for (letter,t) in s.transitions.iter().enumerate() {
+				if let Some(to) = t {
+					edges.push((from,*to,letters[letter].to_string()));
+				}
+			}
+		}
+		return dot("DFA",self.starting,&self.states.iter().map(|s| s.accepting).collect::<Vec<bool>>(),edges);
+	}
+}
+
+impl NFA {
+	/// The NFA in the DOT language, with jumps labelled ε.
+	pub fn to_dot(&self) -> String {
+		let letters:Vec<String> = std::iter::once("ε".to_string()).chain(self.alphabet.chars().map(|c| c.to_string())).collect();
+		let mut edges:Vec<(StateNum,StateNum,String)> = Vec::new();
+		for (from,s) in self.states.iter().enumerate() {
+			for (letter,t) in s.transitions.iter().enumerate() {
+				for to in &t.0 {
+					edges.push((from,*to,letters[letter].clone()));
+				}
+			}
+		}
+		return dot("NFA",self.starting,&self.states.iter().map(|s| s.accepting).collect::<Vec<bool>>(),edges);
+	}
+}
+
+fn dot(name:&str, starting:StateNum, accepting:&[bool], edges:Vec<(StateNum,StateNum,String)>) -> String {
+	let mut labels:Vec<((StateNum,StateNum),Vec<String>)> = Vec::new();
+	let mut position:HashMap<(StateNum,StateNum),usize> = HashMap::new();
+	for (from,to,label) in edges {
+		match position.get(&(from,to)) {
+			Some(i) => labels[*i].1.push(label),
+			None => {
+				position.insert((from,to),labels.len());
+				labels.push(((from,to),vec![label]));
+			}
+		}
+	}
+	let mut lines:Vec<String> = vec![format!("digraph {} {{",name),"\trankdir=LR;".to_string(),"\tstart [shape=point];".to_string()];
+	for (state,accepts) in accepting.iter().enumerate() {
+		let shape = match accepts {
+			true => "doublecircle",
+			false => "circle"
+		};
+		lines.push(format!("\t{} [shape={}];",state+1,shape));
+	}
+	lines.push(format!("\tstart -> {};",starting+1));
+	for ((from,to),joined) in labels {
+		let label = joined.join(",").replace('\\',"\\\\").replace('"',"\\\"");
+		lines.push(format!("\t{} -> {} [label=\"{}\"];",from+1,to+1,label));
+	}
+	lines.push("}".to_string());
+	return lines.join("\n");
+}
@@ -0,0 +1,90 @@
+use crate::Automata;
+use crate::Rslt;
+use crate::error::Error;
+use crate::report::Format;
+use crate::report::Report;
+
+use std::io::BufRead;
+use std::io::Write;
+
+const HELP:&str = "Commands:
+  run WORD       whether the word is accepted (run on its own tests the empty word)
+  trace WORD     each step of the run on the word
+  dfa            print the DFA
+  nfa            print the NFA
+  regex          print a regex
+  minimize       replace the automaton by its minimal DFA
+  complement     replace the language by its complement
+  reverse        replace the language by its reverse
+  write dfa FILE write the DFA to a .dfa file
+  write nfa FILE write the NFA to a .nfa file
+  help           show this list
+  quit           leave the session";
+
+/// Reads commands from the standard input until it ends or `quit` is entered. The conversions between views are cached in the automata, so each is only done once until an operation changes the language.
+pub fn run(mut autos:Automata) -> Rslt {
+	println!("Type help for the list of commands.");
+	// the session always talks in text, whatever --format was given
+	let mut report = Report::new(Format::Text,false);
+	let stdin = std::io::stdin();
+	let mut lines = stdin.lock().lines();
+	loop {
+		print!("> ");
+		if let Err(e) = std::io::stdout().flush() {
+			return Rslt::Err(Error::io(e.to_string()));
+		}
+		let line = match lines.next() {
+			None => {
+				// end the prompt's line
+				println!();
+				return Rslt::Done;
+			},
+			Some(Err(e)) => return Rslt::Err(Error::io(e.to_string())),
+			Some(Ok(line)) => line
+		};
+		let words:Vec<&str> = line.split_whitespace().collect();
+		let result = match words.as_slice() {
+			[] => Ok(()),
+			["quit" | "exit"] => return Rslt::Done,
+			["help"] => {
+				println!("{}",HELP);
+				Ok(())
+			},
+			["run"] => run_word(&mut autos,""),
+			["run",word] => run_word(&mut autos,word),
+			["trace"] => trace_word(&mut autos,""),
+			["trace",word] => trace_word(&mut autos,word),
+			["dfa"] => autos.dfa_text().map(|text| println!("{}",text)),
+			["nfa"] => autos.nfa_text().map(|text| println!("{}",text)),
+			["regex"] => autos.output_regex(&mut report),
+			["minimize"] => autos.minimize(),
+			["complement"] => autos.complement(),
+			["reverse"] => autos.reverse(),
+			["write","dfa",address] => autos.output_dfa(address,&mut report),
+			["write","nfa",address] => autos.output_nfa(address,&mut report),
+			_ => Err(Error::usage(format!("Unknown command {}, type help for the list of commands",line.trim())))
+		};
+		if let Err(e) = result {
+			println!("Error: {}",e);
+		}
+	}
+}
+
+fn run_word(autos:&mut Automata, word:&str) -> Result<(),Error> {
+	return match autos.run(word) {
+		Rslt::Acc => {
+			println!("ACCEPT");
+			Ok(())
+		},
+		Rslt::Rej => {
+			println!("REJECT");
+			Ok(())
+		},
+		Rslt::Err(e) => Err(e),
+		_ => unreachable!("running a word gives a result")
+	};
+}
+
+fn trace_word(autos:&mut Automata, word:&str) -> Result<(),Error> {
+	return autos.trace(word).map(|trace| println!("{}",trace));
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+/// How the results of the program are printed.
+#[derive(clap::ValueEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Format {
+	/// Lines of text, printed as the results are found.
+	Text,
+	/// One JSON object with every result, printed at the end.
+	Json
+}
+
+/// A JSON value, with the fields of an object kept in the order they were added.
+#[derive(Clone,Debug)]
+pub enum Json {
+	Null,
+	Bool(bool),
+	Number(usize),
+	String(String),
+	Array(Vec<Json>),
+	Object(Vec<(String,Json)>)
+}
+
+impl From<bool> for Json {
+	fn from(b:bool) -> Self {
+		return Json::Bool(b);
+	}
+}
+
+impl From<usize> for Json {
+	fn from(n:usize) -> Self {
+		return Json::Number(n);
+	}
+}
+
+impl From<&str> for Json {
+	fn from(s:&str) -> Self {
+		return Json::String(s.to_string());
+	}
+}
+
+impl From<String> for Json {
+	fn from(s:String) -> Self {
+		return Json::String(s);
+	}
+}
+
+impl<T:Into<Json>> From<Option<T>> for Json {
+	fn from(o:Option<T>) -> Self {
+		return match o {
+			None => Json::Null,
+			Some(value) => value.into()
+		};
+	}
+}
+
+impl<T:Into<Json>> From<Vec<T>> for Json {
+	fn from(v:Vec<T>) -> Self {
+		return Json::Array(v.into_iter().map(|value| value.into()).collect());
+	}
+}
+
+fn quote(s:&str) -> String {
+	let mut escaped = String::new();
+	for c in s.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}",c as u32)),
+			c => escaped.push(c)
+		}
+	}
+	return format!("\"{}\"",escaped);
+}
+
+impl fmt::Display for Json {
+	fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+		let output = match self {
+			Self::Null => "null".to_string(),
+			Self::Bool(b) => b.to_string(),
+			Self::Number(n) => n.to_string(),
+			Self::String(s) => quote(s),
+			Self::Array(values) => format!("[{}]",values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(",")),
+			Self::Object(fields) => format!("{{{}}}",fields.iter().map(|(key,value)| format!("{}:{}",quote(key),value)).collect::<Vec<String>>().join(","))
+		};
+		return write!(f,"{}",output);
+	}
+}
+
+/// The results of a run of the program. As text each result is printed when it is found, while as JSON they are gathered into one object printed at the end.
+pub struct Report {
+	pub format:Format,
+	pub quiet:bool,
+	fields:Vec<(String,Json)>
+}
+
+impl Report {
+	pub fn new(format:Format, quiet:bool) -> Report {
+		return Report{format,quiet,fields:Vec::new()};
+	}
+
+	/// A result that was asked for, such as a regex or a list of words, printed as text even with --quiet.
+	pub fn result(&mut self, key:&str, value:impl Into<Json>, text:String) {
+		match self.format {
+			Format::Text => println!("{}",text),
+			Format::Json => self.fields.push((key.to_string(),value.into()))
+		}
+	}
+
+	/// A message about what was done, such as where a file was written, left out of the text with --quiet.
+	pub fn note(&mut self, key:&str, value:impl Into<Json>, text:String) {
+		match self.format {
+			Format::Text if self.quiet => (),
+			Format::Text => println!("{}",text),
+			Format::Json => self.fields.push((key.to_string(),value.into()))
+		}
+	}
+
+	/// Details only given in the JSON, that the text of a result already tells.
+	pub fn data(&mut self, key:&str, value:impl Into<Json>) {
+		if self.format == Format::Json {
+			self.fields.push((key.to_string(),value.into()));
+		}
+	}
+
+	/// The JSON object of every result, followed by the outcome.
+	pub fn into_json(self, outcome:Vec<(String,Json)>) -> Json {
+		let mut fields = self.fields;
+		fields.extend(outcome);
+		return Json::Object(fields);
+	}
+}
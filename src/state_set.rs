@@ -0,0 +1,67 @@
+use crate::StateNum;
+
+const WORD_BITS:usize = 64;
+
+/// A set of NFA states stored as a fixed-width bitset, one bit per state.
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+pub struct StateSet {
+	words:Vec<u64>
+}
+
+impl StateSet {
+	/// An empty set able to hold the states 0 to num_states-1.
+	pub fn new(num_states:usize) -> StateSet {
+		return StateSet{words:vec![0;num_states.div_ceil(WORD_BITS)]};
+	}
+
+	/// The states reachable from the given ones, found with a depth first search.
+	pub fn reachable<I:IntoIterator<Item = StateNum>>(num_states:usize, from:&[StateNum], successors:impl Fn(StateNum) -> I) -> StateSet {
+		let mut visited = StateSet::new(num_states);
+		let mut stack:Vec<StateNum> = Vec::new();
+		for state in from {
+			if visited.insert(*state) {
+				stack.push(*state);
+			}
+		}
+		while let Some(state) = stack.pop() {
+			for next in successors(state) {
+				if visited.insert(next) {
+					stack.push(next);
+				}
+			}
+		}
+		return visited;
+	}
+
+	pub fn insert(&mut self, state:StateNum) -> bool {
+		let (word,bit) = (state / WORD_BITS, 1u64 << (state % WORD_BITS));
+		let added = self.words[word] & bit == 0;
+		self.words[word] |= bit;
+		return added;
+	}
+
+	pub fn contains(&self, state:StateNum) -> bool {
+		return self.words[state / WORD_BITS] & (1u64 << (state % WORD_BITS)) != 0;
+	}
+
+	pub fn union_with(&mut self, other:&StateSet) {
+		for (w,o) in self.words.iter_mut().zip(&other.words) {
+			*w |= *o;
+		}
+	}
+
+	/// The states in the set, in increasing order.
+	pub fn iter(&self) -> impl Iterator<Item = StateNum> + '_ {
+		return self.words.iter().enumerate().flat_map(|(i,word)| {
+			let mut word = *word;
+			std::iter::from_fn(move || {
+				if word == 0 {
+					return None;
+				}
+				let bit = word.trailing_zeros() as usize;
+				word &= word - 1;
+				return Some(i * WORD_BITS + bit);
+			})
+		});
+	}
+}
@@ -0,0 +1,164 @@
+use crate::StateNum;
+use crate::Index1;
+use crate::dfa::DFA;
+use crate::nfa::NFA;
+use crate::state_set::StateSet;
+use crate::report::Json;
+
+use std::fmt;
+
+/// Why a run rejected its word. Positions are counted from 1.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Rejection {
+	/// The whole word was read, but no state reached at the end is accepting.
+	NotAccepting,
+	/// The letter is not in the alphabet of the automaton.
+	NotInAlphabet{position:usize,letter:char},
+	/// No transition reads the letter: a DFA went to the implicit sink state, or an NFA has no states left.
+	NoTransition{position:usize,letter:char}
+}
+
+/// One step of a run: the states the letter moved to, and the states active after following the jumps from them. The first step of a run is its start, reading no letter.
+#[derive(Clone,Debug)]
+pub struct Step {
+	pub letter:Option<char>,
+	pub reached:Vec<StateNum>,
+	pub active:Vec<StateNum>
+}
+
+/// The run of an automaton on a word, step by step. A run rejected part way through has no steps for the rest of the word.
+#[derive(Clone,Debug)]
+pub struct Trace {
+	pub deterministic:bool,
+	pub steps:Vec<Step>,
+	pub rejection:Option<Rejection>
+}
+
+impl Trace {
+	pub fn accepted(&self) -> bool {
+		return self.rejection.is_none();
+	}
+}
+
+impl DFA {
+	/// Runs the DFA on the word, recording each state it passes through.
+	pub fn trace(&self, word:&str) -> Trace {
+		let alphabet_map = crate::get_alphabet_hm(&self.alphabet);
+		let mut steps = vec![Step{letter:None,reached:vec![self.starting],active:vec![self.starting]}];
+		let mut current = self.starting;
+		for (i,letter) in word.chars().enumerate() {
+			let rejection = match alphabet_map.get(&letter) {
+				None => Some(Rejection::NotInAlphabet{position:i+1,letter}),
+				Some(index) => match self.states[current].transitions[index.0] {
+					None => Some(Rejection::NoTransition{position:i+1,letter}),
+					Some(next) => {
+						current = next;
+						None
+					}
+				}
+			};
+			if rejection.is_some() {
+				return Trace{deterministic:true,steps,rejection};
+			}
+			steps.push(Step{letter:Some(letter),reached:vec![current],active:vec![current]});
+		}
+		let rejection = match self.states[current].accepting {
+			true => None,
+			false => Some(Rejection::NotAccepting)
+		};
+		return Trace{deterministic:true,steps,rejection};
+	}
+}
+
+impl NFA {
+	/// Runs the NFA on the word, recording the set of active states after each letter, with the jumps followed from them.
+	pub fn trace(&self, word:&str) -> Trace {
+		let alphabet_map = crate::get_alphabet_hm(&self.alphabet);
+		let closure = |reached:&Vec<StateNum>| -> Vec<StateNum> {
+			return StateSet::reachable(self.states.len(),reached,|state| self.states[state].transitions[0].0.iter().cloned()).iter().collect();
+		};
+		let mut steps = vec![Step{letter:None,reached:vec![self.starting],active:closure(&vec![self.starting])}];
+		for (i,letter) in word.chars().enumerate() {
+			let by = match alphabet_map.get(&letter) {
+				Some(index) => Index1::from(*index),
+				None => return Trace{deterministic:false,steps,rejection:Some(Rejection::NotInAlphabet{position:i+1,letter})}
+			};
+			let mut reached:Vec<StateNum> = steps.last().unwrap().active.iter().flat_map(|s| self.states[*s].transitions[by.0].0.iter().cloned()).collect();
+			reached.sort();
+			reached.dedup();
+			if reached.is_empty() {
+				return Trace{deterministic:false,steps,rejection:Some(Rejection::NoTransition{position:i+1,letter})};
+			}
+			let active = closure(&reached);
+			steps.push(Step{letter:Some(letter),reached,active});
+		}
+		let rejection = match steps.last().unwrap().active.iter().any(|s| self.states[*s].accepting) {
+			true => None,
+			false => Some(Rejection::NotAccepting)
+		};
+		return Trace{deterministic:false,steps,rejection};
+	}
+}
+
+/// Writes the trace with the states numbered from 1, as in .dfa and .nfa files.
+impl fmt::Display for Trace {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let states = |set:&Vec<StateNum>| -> String {
+			return match self.deterministic {
+				true => format!("state {}",set[0]+1),
+				false => format!("{{{}}}",set.iter().map(|s| (s+1).to_string()).collect::<Vec<String>>().join(","))
+			};
+		};
+		let with_jumps = |step:&Step| -> String {
+			return match self.deterministic || step.active == step.reached {
+				true => states(&step.reached),
+				false => format!("{}, with jumps {}",states(&step.reached),states(&step.active))
+			};
+		};
+		let mut lines:Vec<String> = vec![format!("start in {}",with_jumps(&self.steps[0]))];
+		for pair in self.steps.windows(2) {
+			lines.push(format!("read {}: {} -> {}",pair[1].letter.unwrap(),states(&pair[0].active),with_jumps(&pair[1])));
+		}
+		let last = states(&self.steps.last().unwrap().active);
+		lines.push(match (&self.rejection,self.deterministic) {
+			(None,true) => format!("ACCEPT: {} is accepting",last),
+			(None,false) => format!("ACCEPT: {} contains an accepting state",last),
+			(Some(Rejection::NotAccepting),true) => format!("REJECT: {} is not accepting",last),
+			(Some(Rejection::NotAccepting),false) => format!("REJECT: {} contains no accepting state",last),
+			(Some(Rejection::NotInAlphabet{position,letter}),_) => format!("REJECT: the letter {} at position {} is not in the alphabet",letter,position),
+			(Some(Rejection::NoTransition{position,letter}),true) => format!("REJECT: {} has no transition on the letter {} at position {}, so the run goes to the implicit sink state",last,letter,position),
+			(Some(Rejection::NoTransition{position,letter}),false) => format!("REJECT: no state of {} has a transition on the letter {} at position {}",last,letter,position)
+		});
+		return write!(f,"{}",lines.join("\n"));
+	}
+}
+
+/// The trace as JSON, with the states numbered from 1 and each step giving the letter read, the states reached and the states active after the jumps.
+impl From<&Trace> for Json {
+	fn from(trace:&Trace) -> Self {
+		let states = |set:&Vec<StateNum>| Json::from(set.iter().map(|s| s+1).collect::<Vec<usize>>());
+		let steps = trace.steps.iter().map(|step| Json::Object(vec![
+			("letter".to_string(),Json::from(step.letter.map(|c| c.to_string()))),
+			("reached".to_string(),states(&step.reached)),
+			("active".to_string(),states(&step.active))
+		])).collect();
+		let rejection = match &trace.rejection {
+			None => Json::Null,
+			Some(Rejection::NotAccepting) => Json::Object(vec![("reason".to_string(),Json::from("not accepting"))]),
+			Some(Rejection::NotInAlphabet{position,letter}) => Json::Object(vec![
+				("reason".to_string(),Json::from("not in alphabet")),
+				("position".to_string(),Json::from(*position)),
+				("letter".to_string(),Json::from(letter.to_string()))
+			]),
+			Some(Rejection::NoTransition{position,letter}) => Json::Object(vec![
+				("reason".to_string(),Json::from("no transition")),
+				("position".to_string(),Json::from(*position)),
+				("letter".to_string(),Json::from(letter.to_string()))
+			])
+		};
+		return Json::Object(vec![
+			("steps".to_string(),Json::Array(steps)),
+			("rejection".to_string(),rejection)
+		]);
+	}
+}
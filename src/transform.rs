@@ -0,0 +1,130 @@
+use crate::nfa::NFA;
+use crate::dfa::DFA;
+use crate::homomorphism::Homomorphism;
+use crate::error::Error;
+use crate::limits::Limits;
+
+use std::convert::TryFrom;
+use std::collections::HashMap;
+
+/// A transformation of the language of the input, given on the command line with --transform. Those taking another language read it from a .dfa or .nfa file, and those changing the alphabet take a list of letters.
+#[derive(Clone,Debug)]
+pub enum Transform {
+	Concat(String),
+	Union(String),
+	Shuffle(String),
+	Star,
+	Plus,
+	Optional,
+	Prefix,
+	Suffix,
+	Infix,
+	RightQuotient(String),
+	LeftQuotient(String),
+	Hom(String),
+	InverseHom(String),
+	Extend(String),
+	Restrict(String),
+	Rename(HashMap<char,char>),
+	Project(String)
+}
+
+impl Transform {
+	/// Reads a transformation written as `name`, `name=FILE`, `name=LETTERS` or `rename=a:x,b:y`.
+	pub fn parse(text:&str) -> Result<Transform,String> {
+		let (name,file) = match text.split_once('=') {
+			Some((name,file)) => (name,Some(file.to_string())),
+			None => (text,None)
+		};
+		return match (name,file) {
+			("concat",Some(file)) => Ok(Self::Concat(file)),
+			("union",Some(file)) => Ok(Self::Union(file)),
+			("shuffle",Some(file)) => Ok(Self::Shuffle(file)),
+			("star",None) => Ok(Self::Star),
+			("plus",None) => Ok(Self::Plus),
+			("optional",None) => Ok(Self::Optional),
+			("prefix",None) => Ok(Self::Prefix),
+			("suffix",None) => Ok(Self::Suffix),
+			("infix",None) => Ok(Self::Infix),
+			("right-quotient",Some(file)) => Ok(Self::RightQuotient(file)),
+			("left-quotient",Some(file)) => Ok(Self::LeftQuotient(file)),
+			("hom",Some(file)) => Ok(Self::Hom(file)),
+			("inverse-hom",Some(file)) => Ok(Self::InverseHom(file)),
+			("extend",Some(letters)) => crate::get_alphabet(&letters).map(Self::Extend),
+			("restrict",Some(letters)) => crate::get_alphabet(&letters).map(Self::Restrict),
+			("rename",Some(map)) => read_renaming(&map).map(Self::Rename),
+			("project",Some(letters)) => crate::get_alphabet(&letters).map(Self::Project),
+			("concat" | "union" | "shuffle" | "right-quotient" | "left-quotient" | "hom" | "inverse-hom",None) => Err(format!("The transformation {} needs a file, written as {}=FILE",name,name)),
+			("extend" | "restrict" | "project",None) => Err(format!("The transformation {} needs a list of letters, written as {}=LETTERS",name,name)),
+			("rename",None) => Err("The transformation rename needs the new name of each letter, written as rename=a:x,b:y".to_string()),
+			("star" | "plus" | "optional" | "prefix" | "suffix" | "infix",Some(_)) => Err(format!("The transformation {} does not take a file",name)),
+			_ => Err(format!("Unknown transformation {}, expected concat=FILE, union=FILE, shuffle=FILE, star, plus, optional, prefix, suffix, infix, right-quotient=FILE, left-quotient=FILE, hom=FILE, inverse-hom=FILE, extend=LETTERS, restrict=LETTERS, rename=a:x,b:y or project=LETTERS",name))
+		};
+	}
+
+	/// Applies a transformation that keeps a DFA deterministic, or None for the others.
+	pub fn apply_dfa(&self, dfa:&DFA) -> Option<DFA> {
+		return match self {
+			Self::Extend(letters) => Some(dfa.extend_alphabet(letters)),
+			Self::Restrict(letters) => Some(dfa.restrict_alphabet(letters)),
+			_ => None
+		};
+	}
+
+	/// Applies the transformation to the NFA, within the limits for the conversions it needs.
+	pub fn apply(&self, nfa:&NFA, limits:&Limits) -> Result<NFA,Error> {
+		return match self {
+			Self::Concat(file) => crate::read_automaton(file).map(|other| nfa.concatenation(&other)),
+			Self::Union(file) => crate::read_automaton(file).map(|other| nfa.union(&other)),
+			Self::Shuffle(file) => crate::read_automaton(file).map(|other| nfa.shuffle(&other)),
+			Self::Star => Ok(nfa.star()),
+			Self::Plus => Ok(nfa.plus()),
+			Self::Optional => Ok(nfa.optional()),
+			Self::Prefix => Ok(nfa.prefix_closure()),
+			Self::Suffix => Ok(nfa.suffix_closure()),
+			Self::Infix => Ok(nfa.infix_closure()),
+			Self::RightQuotient(file) => crate::read_automaton(file).map(|other| nfa.right_quotient(&other)),
+			Self::LeftQuotient(file) => crate::read_automaton(file).map(|other| nfa.left_quotient(&other)),
+			Self::Hom(file) => match read_homomorphism(file) {
+				Err(e) => Err(e),
+				Ok(h) => nfa.apply_homomorphism(&h).map_err(Error::usage)
+			},
+			Self::InverseHom(file) => match read_homomorphism(file) {
+				Err(e) => Err(e),
+				Ok(h) => match DFA::try_from_nfa(nfa,limits) {
+					Err(e) => Err(Error::from(e)),
+					Ok(dfa) => Ok(NFA::from(&dfa.inverse_homomorphism(&h)))
+				}
+			},
+			Self::Extend(letters) => Ok(nfa.extend_alphabet(letters)),
+			Self::Restrict(letters) => Ok(nfa.restrict_alphabet(letters)),
+			Self::Rename(map) => nfa.rename_letters(map).map_err(Error::usage),
+			Self::Project(letters) => Ok(nfa.project(letters))
+		};
+	}
+}
+
+/// Reads a renaming written as a comma separated list of `letter:new-letter`.
+fn read_renaming(text:&str) -> Result<HashMap<char,char>,String> {
+	let mut map:HashMap<char,char> = HashMap::new();
+	for pair in text.split(',') {
+		let letters:Vec<char> = pair.chars().collect();
+		if letters.len() != 3 || letters[1] != ':' {
+			return Err(format!("Each renaming must be written as letter:new-letter, not {}",pair));
+		}
+		if letters[2] == ',' || letters[2] == ':' {
+			return Err(format!("The alphabet cannot contain {}",letters[2]));
+		}
+		if map.insert(letters[0],letters[2]).is_some() {
+			return Err(format!("The letter {} is renamed more than once",letters[0]));
+		}
+	}
+	return Ok(map);
+}
+
+fn read_homomorphism(file:&str) -> Result<Homomorphism,Error> {
+	return match crate::read_input_file(file) {
+		Err(e) => Err(e),
+		Ok(lines) => Homomorphism::try_from(lines).map_err(|e| Error::parse(format!("{} ({})",e,file)))
+	};
+}